@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+pub struct Column {
+  pub name: String,
+  pub sql_type: String,
+  pub rust_type: String,
+  pub nullable: bool,
+}
+
+pub fn generate(schema_dir: &Path, out_dir: &Path) -> Result<Vec<PathBuf>, String> {
+  let mut schema_files: Vec<PathBuf> = fs::read_dir(schema_dir)
+    .map_err(|err| format!("failed to read schema dir! dir={}, error={}", schema_dir.display(), err))?
+    .filter_map(|file| file.ok().map(|file| file.path()))
+    .filter(|path| path.extension().and_then(|extension| extension.to_str()) == Some("json"))
+    .collect();
+  schema_files.sort();
+  fs::create_dir_all(out_dir).map_err(|err| format!("failed to create out dir! dir={}, error={}", out_dir.display(), err))?;
+
+  let mut generated = Vec::new();
+  for schema_file in schema_files.iter() {
+    let json_str = fs::read_to_string(schema_file).map_err(|err| format!("file={}: {}", schema_file.display(), err))?;
+    let json_schema: Value = serde_json::from_str(json_str.as_str()).map_err(|err| format!("file={}: {}", schema_file.display(), err))?;
+    let schema_map = json_schema.as_object().ok_or(format!("file={}: schema file must be object of schemas!", schema_file.display()))?;
+    for (schema_name, schema) in schema_map.iter() {
+      let columns = columns(schema_name, schema).map_err(|err| format!("file={}, schema={}: {}", schema_file.display(), schema_name, err))?;
+      let table_path = out_dir.join(format!("{}_table.rs", schema_name));
+      let model_path = out_dir.join(format!("{}_model.rs", schema_name));
+      fs::write(&table_path, table_source(schema_name, &columns)).map_err(|err| err.to_string())?;
+      fs::write(&model_path, model_source(schema_name, &columns)).map_err(|err| err.to_string())?;
+      generated.push(table_path);
+      generated.push(model_path);
+    }
+  }
+  Ok(generated)
+}
+
+pub fn columns(schema_name: &str, schema: &Value) -> Result<Vec<Column>, String> {
+  let schema = schema.as_object().ok_or("schema must be object!")?;
+  let attributes = schema.get("attributes").and_then(|attributes| attributes.as_object()).ok_or("attributes must be object!")?;
+  let versioned = schema.get("versioned").and_then(|versioned| versioned.as_bool()).unwrap_or(false);
+
+  let mut columns = vec!(column(format!("{}_id", schema_name), "integer", None, false)?);
+  let mut names: Vec<&String> = attributes.keys().collect();
+  names.sort();
+  for name in names {
+    let attribute = &attributes[name];
+    let (_type, nullable) = attribute_type(attribute).map_err(|err| format!("attribute={}: {}", name, err))?;
+    let items = attribute.get("items").and_then(|items| items.get("type")).and_then(|items_type| items_type.as_str());
+    columns.push(column(name.clone(), _type.as_str(), items, nullable).map_err(|err| format!("attribute={}: {}", name, err))?);
+  }
+  if versioned {
+    if !attributes.contains_key("version") {
+      columns.push(column(String::from("version"), "integer", None, false)?);
+    }
+    columns.push(column(String::from("valid_from"), "timestamp", None, false)?);
+    columns.push(column(String::from("valid_to"), "timestamp", None, true)?);
+  }
+  Ok(columns)
+}
+
+fn attribute_type(attribute: &Value) -> Result<(String, bool), String> {
+  match attribute.get("type") {
+    Some(Value::String(_type)) => Ok((_type.clone(), false)),
+    Some(Value::Array(types)) => match types.iter().map(|_type| _type.as_str()).collect::<Vec<Option<&str>>>().as_slice() {
+      [Some(_type)] => Ok((String::from(*_type), false)),
+      [Some(_type), Some("null")] => Ok((String::from(*_type), true)),
+      _ => Err(String::from("type must be a type name, or an array of a type name and null!")),
+    },
+    _ => Err(String::from("attribute must include type!")),
+  }
+}
+
+fn column(name: String, _type: &str, items: Option<&str>, nullable: bool) -> Result<Column, String> {
+  let (sql_type, rust_type) = match (_type, items) {
+    ("array", Some(items_type)) => {
+      let (sql_type, rust_type) = scalar_type(items_type)?;
+      (format!("Array<{}>", sql_type), format!("Vec<{}>", rust_type))
+    }
+    (_type, _) => {
+      let (sql_type, rust_type) = scalar_type(_type)?;
+      (String::from(sql_type), String::from(rust_type))
+    }
+  };
+  Ok(Column { name, sql_type, rust_type, nullable })
+}
+
+fn scalar_type(_type: &str) -> Result<(&'static str, &'static str), String> {
+  let converted = match _type {
+    "string" | "array" => ("Text", "String"),
+    "integer" => ("BigInt", "i64"),
+    "number" => ("Double", "f64"),
+    "boolean" => ("Bool", "bool"),
+    "object" => ("Jsonb", "serde_json::Value"),
+    "timestamp" => ("Timestamptz", "chrono::DateTime<chrono::Utc>"),
+    "numeric" => ("Numeric", "crate::model::numeric::Numeric"),
+    "bytea" => ("Binary", "Vec<u8>"),
+    _ => return Err(format!("{} type is not supported by cv-server!", _type)),
+  };
+  Ok(converted)
+}
+
+pub fn table_source(schema_name: &str, columns: &[Column]) -> String {
+  let mut lines = Vec::new();
+  lines.push(String::from("table! {"));
+  lines.push(format!("  {} ({}_id) {{", schema_name, schema_name));
+  for column in columns.iter() {
+    if column.nullable {
+      lines.push(format!("    {} -> Nullable<{}>,", column.name, column.sql_type));
+    } else {
+      lines.push(format!("    {} -> {},", column.name, column.sql_type));
+    }
+  }
+  lines.push(String::from("  }"));
+  lines.push(String::from("}"));
+  lines.join("\n") + "\n"
+}
+
+pub fn model_source(schema_name: &str, columns: &[Column]) -> String {
+  let mut lines = Vec::new();
+  lines.push(String::from("#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Apiv2Schema)]"));
+  lines.push(format!("pub struct {} {{", struct_name(schema_name)));
+  for column in columns.iter() {
+    if column.nullable {
+      lines.push(format!("  {}: Option<{}>,", column.name, column.rust_type));
+    } else {
+      lines.push(format!("  {}: {},", column.name, column.rust_type));
+    }
+  }
+  lines.push(String::from("}"));
+  lines.join("\n") + "\n"
+}
+
+pub fn struct_name(schema_name: &str) -> String {
+  schema_name.split("_").filter(|word| !word.is_empty()).map(|word| {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+  }).collect()
+}
+
+#[cfg(test)]
+mod codegen {
+  use serde_json::json;
+
+  use crate::{columns, model_source, struct_name, table_source};
+
+  #[test]
+  fn struct_name_test() {
+    assert_eq!(struct_name("cosmos_block"), "CosmosBlock");
+    assert_eq!(struct_name("cosmos_tx"), "CosmosTx");
+  }
+
+  #[test]
+  fn generate_test() {
+    let schema = json!({
+      "attributes": {
+        "time": { "type": [ "timestamp", "null" ] },
+        "height": { "type": "integer" },
+        "signers": { "type": "array", "items": { "type": "string" } },
+        "fee": { "type": [ "object", "null" ] }
+      },
+      "indexes": [],
+      "uniques": []
+    });
+    let columns = columns("test_block", &schema).unwrap();
+    assert_eq!(table_source("test_block", &columns), "table! {
+  test_block (test_block_id) {
+    test_block_id -> BigInt,
+    fee -> Nullable<Jsonb>,
+    height -> BigInt,
+    signers -> Array<Text>,
+    time -> Nullable<Timestamptz>,
+  }
+}
+");
+    assert_eq!(model_source("test_block", &columns), "#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Apiv2Schema)]
+pub struct TestBlock {
+  test_block_id: i64,
+  fee: Option<serde_json::Value>,
+  height: i64,
+  signers: Vec<String>,
+  time: Option<chrono::DateTime<chrono::Utc>>,
+}
+");
+  }
+
+  #[test]
+  fn numeric_test() {
+    let schema = json!({
+      "attributes": {
+        "amount": { "type": "numeric" },
+        "fees": { "type": [ "array", "null" ], "items": { "type": "numeric" } }
+      }
+    });
+    let columns = columns("test_tx", &schema).unwrap();
+    assert!(table_source("test_tx", &columns).contains("    amount -> Numeric,\n    fees -> Nullable<Array<Numeric>>,\n"));
+    assert!(model_source("test_tx", &columns).contains("  amount: crate::model::numeric::Numeric,\n  fees: Option<Vec<crate::model::numeric::Numeric>>,\n"));
+  }
+
+  #[test]
+  fn unsupported_type_test() {
+    let schema = json!({ "attributes": { "amount": { "type": "decimal" } } });
+    assert!(columns("test_tx", &schema).is_err());
+  }
+}
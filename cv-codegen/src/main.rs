@@ -0,0 +1,22 @@
+use std::env;
+use std::path::Path;
+use std::process;
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  if args.len() != 3 {
+    eprintln!("usage: cv-codegen <schema_dir> <out_dir>");
+    process::exit(2);
+  }
+  match cv_codegen::generate(Path::new(&args[1]), Path::new(&args[2])) {
+    Ok(generated) => {
+      for path in generated.iter() {
+        println!("{}", path.display());
+      }
+    }
+    Err(err) => {
+      eprintln!("{}", err);
+      process::exit(1);
+    }
+  }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use cpu_time::ProcessTime;
+use criterion::{Criterion, criterion_group, criterion_main, Throughput};
+use serde_json::{json, Value};
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+
+use cv_daemon::error::error::ExpectedError;
+use cv_daemon::libs::shutdown::ShutdownSignal;
+use cv_daemon::libs::sink::Sink;
+use cv_daemon::plugin::postgres::{Postgres, PostgresMsg, Sinks};
+use cv_daemon::plugin::slack::{Slack, SlackMsg};
+use cv_daemon::types::channel::MultiSender;
+use cv_daemon::types::postgres::PostgresSchema;
+
+const MESSAGES: usize = 1000;
+const IDLE_WINDOW: Duration = Duration::from_millis(200);
+const LOOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// takes the rows without writing them, so only the postgres loop is measured.
+struct NoopSink;
+
+impl Sink for NoopSink {
+  fn write(&mut self, _schema: &PostgresSchema, _rows: &mut Vec<Value>, _version: i64, _height: u64, _bulk: bool) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+
+  fn flush(&mut self) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+
+  fn flush_idle(&mut self) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Loop {
+  Slack,
+  Postgres,
+}
+
+impl Loop {
+  // starts the plugin loop on its own channel. the loop ends once the sender is dropped.
+  fn start(&self, monitor: broadcast::Receiver<Value>) -> ShutdownSignal {
+    let shutdown = ShutdownSignal::new();
+    match self {
+      // slack::activate is not set, so no hook is called.
+      Loop::Slack => Slack::recv(HashMap::new(), monitor, shutdown.clone()),
+      Loop::Postgres => {
+        let schema = PostgresSchema::from(String::from("bench_block"), &json!({
+          "attributes": { "height": { "type": "integer" } },
+          "indexes": [],
+          "uniques": [],
+          "sinks": [ "postgres" ]
+        })).unwrap();
+        let mut sinks: Sinks = HashMap::new();
+        sinks.insert(String::from("postgres"), Box::new(NoopSink));
+        let schema_map = HashMap::from([(schema.schema_name.clone(), schema)]);
+        Postgres::process(sinks, schema_map, MultiSender::new(vec!()), monitor, shutdown.clone());
+      }
+    }
+    shutdown
+  }
+
+  fn msg(&self, height: usize) -> Value {
+    match self {
+      Loop::Slack => SlackMsg::new(String::from("info"), format!("height={}", height)),
+      Loop::Postgres => PostgresMsg::new(String::from("bench_block"), json!({"height": height}), -1, height as u64, false),
+    }
+  }
+}
+
+fn throughput(runtime: &Runtime, plugin_loop: Loop) {
+  let _guard = runtime.enter();
+  let (sender, monitor) = broadcast::channel(MESSAGES);
+  let shutdown = plugin_loop.start(monitor);
+  for height in 0..MESSAGES {
+    let _ = sender.send(plugin_loop.msg(height));
+  }
+  drop(sender);
+  assert!(shutdown.wait(LOOP_TIMEOUT));
+}
+
+fn idle_cpu(runtime: &Runtime, plugin_loop: Loop) -> Duration {
+  let _guard = runtime.enter();
+  let (sender, monitor) = broadcast::channel::<Value>(16);
+  let shutdown = plugin_loop.start(monitor);
+  let start = ProcessTime::now();
+  std::thread::sleep(IDLE_WINDOW);
+  let elapsed = start.elapsed();
+  drop(sender);
+  assert!(shutdown.wait(LOOP_TIMEOUT));
+  elapsed
+}
+
+fn plugin_loop_benchmark(c: &mut Criterion) {
+  let runtime = Runtime::new().unwrap();
+
+  let mut group = c.benchmark_group("throughput");
+  group.throughput(Throughput::Elements(MESSAGES as u64));
+  group.sample_size(10);
+  group.bench_function("slack", |b| b.iter(|| throughput(&runtime, Loop::Slack)));
+  group.bench_function("postgres", |b| b.iter(|| throughput(&runtime, Loop::Postgres)));
+  group.finish();
+
+  // reports cpu time spent per idle window instead of wall time.
+  let mut group = c.benchmark_group("idle_cpu");
+  group.sample_size(10);
+  group.bench_function("slack", |b| b.iter_custom(|iters| (0..iters).map(|_| idle_cpu(&runtime, Loop::Slack)).sum()));
+  group.bench_function("postgres", |b| b.iter_custom(|iters| (0..iters).map(|_| idle_cpu(&runtime, Loop::Postgres)).sum()));
+  group.finish();
+}
+
+criterion_group!(benches, plugin_loop_benchmark);
+criterion_main!(benches);
@@ -76,6 +76,12 @@ impl From<r2d2_postgres::postgres::Error> for ExpectedError {
   }
 }
 
+impl From<r2d2_postgres::r2d2::Error> for ExpectedError {
+  fn from(err: r2d2_postgres::r2d2::Error) -> Self {
+    ExpectedError::PostgresError(err.to_string())
+  }
+}
+
 impl From<std::io::Error> for ExpectedError {
   fn from(err: std::io::Error) -> Self {
     ExpectedError::IoError(err.to_string())
@@ -0,0 +1,7 @@
+// the tests of each module live in a module of the same name.
+#![allow(clippy::module_inception)]
+
+pub mod plugin;
+pub mod types;
+pub mod libs;
+pub mod error;
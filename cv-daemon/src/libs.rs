@@ -1,6 +1,17 @@
+pub mod alert;
+pub mod archive;
+pub mod backfill;
+pub mod check;
 pub mod serde;
 pub mod opt;
 pub mod request;
 pub mod postgres;
+pub mod migration;
+pub mod sink;
+pub mod shutdown;
+pub mod notice;
 pub mod sync_helper;
+pub mod transform;
+pub mod uptime;
+pub mod email;
 pub mod error;
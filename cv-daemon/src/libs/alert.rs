@@ -0,0 +1,311 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use appbase::prelude::*;
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs;
+use crate::libs::serde::{get_string, get_u64};
+use crate::libs::notice::Notice;
+use crate::plugin::alert::{InsertStatMsg, SyncHealthMsg};
+use crate::plugin::alert::AlertRule;
+use crate::plugin::slack::SlackMsgLevel;
+use crate::types::enumeration::Enumeration;
+use crate::types::sync::{SyncState, SyncStatus};
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+// the insert error rate is not judged on a handful of writes.
+const MIN_INSERT_WRITES: u64 = 10;
+
+#[derive(Clone, Debug)]
+pub struct AlertRules {
+  pub lag_blocks: Option<u64>,
+  pub stall: Option<Duration>,
+  pub failover: bool,
+  pub insert_error_rate: Option<f64>,
+  pub insert_error_window: Duration,
+  pub sync_error: bool,
+}
+
+impl AlertRules {
+  pub fn load() -> Self {
+    if !libs::opt::get_value::<bool>("alert::activate").unwrap_or(false) {
+      return Self::disabled();
+    }
+    AlertRules {
+      lag_blocks: libs::opt::get_value::<u64>("alert::lag-blocks").ok().filter(|lag_blocks| *lag_blocks > 0),
+      stall: libs::opt::get_value::<u64>("alert::stall-minutes").ok().filter(|minutes| *minutes > 0).map(|minutes| Duration::from_secs(minutes * 60)),
+      failover: libs::opt::get_value::<bool>("alert::failover").unwrap_or(false),
+      insert_error_rate: libs::opt::get_value::<f64>("alert::insert-error-rate").ok().filter(|rate| *rate > 0.0),
+      insert_error_window: Duration::from_millis(libs::opt::get_value::<u64>("alert::insert-error-window").unwrap_or(300000)),
+      sync_error: libs::opt::get_value::<bool>("alert::sync-error").unwrap_or(false),
+    }
+  }
+
+  pub fn disabled() -> Self {
+    AlertRules { lag_blocks: None, stall: None, failover: false, insert_error_rate: None, insert_error_window: Duration::from_secs(300), sync_error: false }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncHealth {
+  pub sync_id: String,
+  pub sync_idx: u64,
+  pub chain_head: u64,
+  pub endpoint: String,
+  pub endpoint_idx: u64,
+  pub status: String,
+  pub error_msg: String,
+}
+
+impl SyncHealth {
+  pub fn from(params: &Map<String, Value>) -> Result<Self, ExpectedError> {
+    Ok(SyncHealth {
+      sync_id: get_string(params, "sync_id")?,
+      sync_idx: get_u64(params, "sync_idx")?,
+      chain_head: get_u64(params, "chain_head")?,
+      endpoint: get_string(params, "endpoint")?,
+      endpoint_idx: get_u64(params, "endpoint_idx")?,
+      status: get_string(params, "status")?,
+      error_msg: get_string(params, "error_msg")?,
+    })
+  }
+}
+
+struct Check {
+  rule: AlertRule,
+  sync_id: Option<String>,
+  height: Option<u64>,
+  level: SlackMsgLevel,
+  // (fingerprint, msg). a firing check alerts again when its fingerprint changes.
+  firing: Option<(String, String)>,
+  detail: String,
+}
+
+pub struct AlertEvaluator {
+  rules: AlertRules,
+  syncs: HashMap<String, (SyncHealth, Instant)>,
+  writes: VecDeque<(Instant, u64, u64)>,
+  firing: HashMap<String, String>,
+}
+
+impl AlertEvaluator {
+  pub fn new(rules: AlertRules) -> Self {
+    AlertEvaluator {
+      rules,
+      syncs: HashMap::new(),
+      writes: VecDeque::new(),
+      firing: HashMap::new(),
+    }
+  }
+
+  pub fn observe_sync(&mut self, health: SyncHealth, now: Instant) {
+    let progressed_at = match self.syncs.get(&health.sync_id) {
+      Some((last, progressed_at)) if last.sync_idx >= health.sync_idx => *progressed_at,
+      _ => now,
+    };
+    self.syncs.insert(health.sync_id.clone(), (health, progressed_at));
+  }
+
+  pub fn observe_inserts(&mut self, writes: u64, errors: u64, now: Instant) {
+    self.writes.push_back((now, writes, errors));
+  }
+
+  /// evaluates every rule and returns the alerts and resolves of the checks whose state changed.
+  pub fn evaluate(&mut self, now: Instant) -> Vec<Notice> {
+    let mut checks = Vec::new();
+    for (health, progressed_at) in self.syncs.values() {
+      checks.extend(self.sync_checks(health, now.duration_since(*progressed_at)));
+    }
+    if let Some(max_rate) = self.rules.insert_error_rate {
+      let window = self.rules.insert_error_window;
+      while self.writes.front().is_some_and(|(written_at, _, _)| now.duration_since(*written_at) >= window) {
+        self.writes.pop_front();
+      }
+      let (writes, errors) = self.writes.iter().fold((0, 0), |(writes, errors), (_, w, e)| (writes + w, errors + e));
+      let rate = if writes > 0 { errors as f64 / writes as f64 } else { 0.0 };
+      let detail = format!("postgres insert error rate is {:.3} ({}/{}) in {} sec. threshold={}", rate, errors, writes, window.as_secs(), max_rate);
+      checks.push(Check {
+        rule: AlertRule::InsertErrorRate,
+        sync_id: None,
+        height: None,
+        level: SlackMsgLevel::Error,
+        firing: if writes >= MIN_INSERT_WRITES && rate > max_rate { Some((String::new(), detail.clone())) } else { None },
+        detail,
+      });
+    }
+    checks.into_iter().filter_map(|check| self.transition(check)).collect()
+  }
+
+  fn sync_checks(&self, health: &SyncHealth, stalled: Duration) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let working = health.status == SyncStatus::Working.value();
+    let check = |rule: AlertRule, level: SlackMsgLevel, firing: Option<(String, String)>, detail: String| Check {
+      rule,
+      sync_id: Some(health.sync_id.clone()),
+      height: Some(health.sync_idx),
+      level,
+      firing,
+      detail,
+    };
+    if let Some(max_lag) = self.rules.lag_blocks {
+      let lag = health.chain_head.saturating_sub(health.sync_idx);
+      let detail = format!("sync is {} blocks behind chain head. sync_idx={}, chain_head={}, threshold={}", lag, health.sync_idx, health.chain_head, max_lag);
+      checks.push(check(AlertRule::Lag, SlackMsgLevel::Warn, if working && lag > max_lag { Some((String::new(), detail.clone())) } else { None }, detail));
+    }
+    if let Some(stall) = self.rules.stall {
+      let detail = format!("no progress for {} min. sync_idx={}, last_error={}", stalled.as_secs() / 60, health.sync_idx, health.error_msg);
+      checks.push(check(AlertRule::Stall, SlackMsgLevel::Error, if working && stalled >= stall { Some((String::new(), detail.clone())) } else { None }, detail));
+    }
+    if self.rules.failover {
+      let detail = format!("endpoint failover happened! active endpoint={}, endpoint_idx={}", health.endpoint, health.endpoint_idx);
+      checks.push(check(AlertRule::Failover, SlackMsgLevel::Warn, if health.endpoint_idx > 0 { Some((health.endpoint.clone(), detail.clone())) } else { None }, format!("back on endpoint={}", health.endpoint)));
+    }
+    if self.rules.sync_error {
+      let detail = format!("sync is in error status! error={}", health.error_msg);
+      checks.push(check(AlertRule::SyncError, SlackMsgLevel::Error, if health.status == SyncStatus::Error.value() { Some((String::new(), detail.clone())) } else { None }, format!("sync status={}", health.status)));
+    }
+    checks
+  }
+
+  fn transition(&mut self, check: Check) -> Option<Notice> {
+    let key = format!("{}|{}", check.rule.value(), check.sync_id.clone().unwrap_or_default());
+    let (level, msg) = match (self.firing.get(&key), check.firing) {
+      (Some(fingerprint), Some((new_fingerprint, _))) if *fingerprint == new_fingerprint => return None,
+      (_, Some((fingerprint, msg))) => {
+        self.firing.insert(key, fingerprint);
+        (check.level.value(), format!("[ALERT] {}: {}", check.rule.value(), msg))
+      }
+      (Some(_), None) => {
+        self.firing.remove(&key);
+        (SlackMsgLevel::Info.value(), format!("[RESOLVED] {}: {}", check.rule.value(), check.detail))
+      }
+      (None, None) => return None,
+    };
+    Some(Notice { level, msg, sync_id: check.sync_id, height: check.height, repeated: 0 })
+  }
+}
+
+/// reports the sync health to the alert plugin at most once a second, or right away when the status or endpoint changes.
+pub struct HealthReporter {
+  reported: Option<(Instant, String, u16)>,
+}
+
+impl HealthReporter {
+  pub fn new() -> Self {
+    HealthReporter { reported: None }
+  }
+
+  pub fn report(&mut self, sender: &Sender, sync_state: &SyncState, chain_head: u64) {
+    let now = Instant::now();
+    let status = sync_state.status.value();
+    if let Some((reported_at, last_status, last_endpoint_idx)) = &self.reported {
+      if now.duration_since(*reported_at) < REPORT_INTERVAL && *last_status == status && *last_endpoint_idx == sync_state.endpoint_idx {
+        return;
+      }
+    }
+    let _ = sender.send(SyncHealthMsg::new(sync_state.sync_id.clone(), sync_state.sync_idx, chain_head, sync_state.active_node(), u64::from(sync_state.endpoint_idx), status.clone(), sync_state.error_msg.clone()));
+    self.reported = Some((now, status, sync_state.endpoint_idx));
+  }
+}
+
+impl Default for HealthReporter {
+  fn default() -> Self {
+    HealthReporter::new()
+  }
+}
+
+/// counts postgres writes and reports them to the alert plugin at most once a second.
+pub struct InsertStats {
+  writes: u64,
+  errors: u64,
+  reported_at: Instant,
+}
+
+impl InsertStats {
+  pub fn new() -> Self {
+    InsertStats { writes: 0, errors: 0, reported_at: Instant::now() }
+  }
+
+  pub fn record(&mut self, succeeded: bool) {
+    self.writes += 1;
+    if !succeeded {
+      self.errors += 1;
+    }
+  }
+
+  pub fn report(&mut self, sender: &Sender) {
+    let now = Instant::now();
+    if self.writes == 0 || now.duration_since(self.reported_at) < REPORT_INTERVAL {
+      return;
+    }
+    let _ = sender.send(InsertStatMsg::new(self.writes, self.errors));
+    self.writes = 0;
+    self.errors = 0;
+    self.reported_at = now;
+  }
+}
+
+impl Default for InsertStats {
+  fn default() -> Self {
+    InsertStats::new()
+  }
+}
+
+#[cfg(test)]
+mod alert {
+  use std::time::{Duration, Instant};
+
+  use crate::libs::alert::{AlertEvaluator, AlertRules, SyncHealth};
+
+  fn health(sync_idx: u64, chain_head: u64, endpoint_idx: u64, status: &str) -> SyncHealth {
+    SyncHealth {
+      sync_id: String::from("cosmos::cosmoshub-4::block_sync"),
+      sync_idx,
+      chain_head,
+      endpoint: format!("https://node-{}", endpoint_idx),
+      endpoint_idx,
+      status: String::from(status),
+      error_msg: String::new(),
+    }
+  }
+
+  fn messages(evaluator: &mut AlertEvaluator, now: Instant) -> Vec<String> {
+    evaluator.evaluate(now).into_iter().map(|notice| format!("{} {}", notice.level, notice.msg.split(':').next().unwrap())).collect()
+  }
+
+  #[test]
+  fn evaluate_test() {
+    let rules = AlertRules {
+      lag_blocks: Some(100),
+      stall: Some(Duration::from_secs(600)),
+      failover: true,
+      insert_error_rate: Some(0.1),
+      insert_error_window: Duration::from_secs(300),
+      sync_error: true,
+    };
+    let mut evaluator = AlertEvaluator::new(rules);
+    let now = Instant::now();
+
+    evaluator.observe_sync(health(10, 500, 0, "Working"), now);
+    assert_eq!(messages(&mut evaluator, now), vec!("warn [ALERT] lag"));
+    assert!(messages(&mut evaluator, now).is_empty());
+
+    evaluator.observe_sync(health(450, 500, 1, "Working"), now + Duration::from_secs(1));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(1)), vec!("info [RESOLVED] lag", "warn [ALERT] failover"));
+    evaluator.observe_sync(health(450, 500, 2, "Working"), now + Duration::from_secs(2));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(2)), vec!("warn [ALERT] failover"));
+
+    evaluator.observe_sync(health(450, 500, 2, "Error"), now + Duration::from_secs(700));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(700)), vec!("error [ALERT] sync_error"));
+    evaluator.observe_sync(health(450, 500, 2, "Working"), now + Duration::from_secs(701));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(701)), vec!("error [ALERT] stall", "info [RESOLVED] sync_error"));
+
+    evaluator.observe_inserts(5, 0, now);
+    assert!(messages(&mut evaluator, now + Duration::from_secs(702)).is_empty());
+    evaluator.observe_inserts(10, 3, now + Duration::from_secs(702));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(702)), vec!("error [ALERT] insert_error_rate"));
+    assert_eq!(messages(&mut evaluator, now + Duration::from_secs(1003)), vec!("info [RESOLVED] insert_error_rate"));
+  }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::error::error::ExpectedError;
+use crate::libs;
+use crate::types::cosmos;
+use crate::types::cosmos::{BlockResponse, TxResponse};
+
+const HEIGHTS_PER_DIR: u64 = 10000;
+
+/// the block body and the body of every txs page as they were received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedBlock {
+  pub block: String,
+  #[serde(default)]
+  pub txs: Vec<String>,
+}
+
+impl ArchivedBlock {
+  /// the bodies are decoded at reindex, so a reindex with a fixed decoder reads what the endpoint sent.
+  pub fn decode(&self) -> Result<(BlockResponse, Vec<TxResponse>), ExpectedError> {
+    Ok((cosmos::decode("block", self.block.as_str())?, cosmos::decode_txs(&self.txs)?))
+  }
+}
+
+/// gzip compressed block and txs response bodies, one file per height at `<dir>/<chain_id>/<height / 10000>/<height>.json.gz`.
+pub struct Archive {
+  dir: PathBuf,
+}
+
+impl Archive {
+  pub fn new(dir: String, chain_id: &str) -> Self {
+    Archive { dir: PathBuf::from(dir).join(chain_id) }
+  }
+
+  /// the archive of the chain if `archive::activate` is set. a reindex job always reads from it.
+  pub fn load(chain_id: &str) -> Option<Self> {
+    if !Self::activated() && !libs::backfill::reindex_activated() {
+      return None;
+    }
+    let dir = libs::opt::get_value_str("archive::dir").unwrap_or(String::from("archive/"));
+    Some(Archive::new(dir, chain_id))
+  }
+
+  pub fn activated() -> bool {
+    libs::opt::get_value::<bool>("archive::activate").unwrap_or(false)
+  }
+
+  fn path(&self, height: u64) -> PathBuf {
+    self.dir.join((height / HEIGHTS_PER_DIR).to_string()).join(format!("{}.json.gz", height))
+  }
+
+  /// writes to a temporary file first, so a crash never leaves a truncated height behind.
+  pub fn write(&self, height: u64, block: &str, txs: &Vec<String>) -> Result<(), ExpectedError> {
+    let path = self.path(height);
+    fs::create_dir_all(path.parent().unwrap())?;
+    let temp_path = path.with_extension("gz.tmp");
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(&temp_path)?), Compression::default());
+    serde_json::to_writer(&mut encoder, &serde_json::json!({"block": block, "txs": txs}))?;
+    encoder.finish()?.flush()?;
+    fs::rename(temp_path, path)?;
+    Ok(())
+  }
+
+  /// returns none if the height was never archived.
+  pub fn read(&self, height: u64) -> Result<Option<ArchivedBlock>, ExpectedError> {
+    let file = match File::open(self.path(height)) {
+      Ok(file) => file,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(ExpectedError::from(err)),
+    };
+    let archived = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+      .map_err(|err| ExpectedError::ParsingError(format!("archived response is broken! height={}, error={}", height, err)))?;
+    Ok(Some(archived))
+  }
+}
+
+#[cfg(test)]
+mod archive {
+  use std::fs;
+
+  use serde_json::json;
+
+  use crate::libs::archive::Archive;
+
+  #[test]
+  fn write_read_test() {
+    let block = json!({
+      "block_id": { "hash": "8F3A", "parts": {} },
+      "block": {
+        "header": {
+          "version": { "block": "11" }, "chain_id": "cosmoshub-4", "height": "10001", "time": "2021-08-01T00:00:00Z",
+          "last_block_id": { "hash": "7E21", "parts": {} }, "last_commit_hash": "C1", "data_hash": "D1", "validators_hash": "E1",
+          "next_validators_hash": "E1", "consensus_hash": "F1", "app_hash": "G1", "last_results_hash": "H1", "evidence_hash": "", "proposer_address": "5771"
+        },
+        "data": { "txs": [ "CpIB" ] },
+        "evidence": {},
+        "last_commit": { "height": "10000", "round": 0, "block_id": { "hash": "7E21", "parts": {} }, "signatures": [] }
+      }
+    });
+    let block = serde_json::to_string_pretty(&block).unwrap();
+    let txs = json!({
+      "tx_responses": [ {
+        "height": "10001", "txhash": "AB12", "code": 0, "gas_wanted": "200000", "gas_used": "81000",
+        "tx": {}, "timestamp": "2021-08-01T00:00:00Z", "logs": []
+      } ],
+      "pagination": { "next_key": null, "total": "1" }
+    }).to_string();
+    let dir = std::env::temp_dir().join(format!("cv-archive-{}", std::process::id())).display().to_string();
+    let archive = Archive::new(dir.clone(), "cosmoshub-4");
+
+    archive.write(10001, &block, &vec!(txs.clone())).unwrap();
+    let archived = archive.read(10001).unwrap().unwrap();
+    assert_eq!(archived.block, block);
+    assert_eq!(archived.txs, vec!(txs));
+    let (block, txs) = archived.decode().unwrap();
+    assert_eq!(block.block.header.height, 10001);
+    assert_eq!(block.num_txs(), 1);
+    assert_eq!(txs[0].txhash, "AB12");
+    assert_eq!(txs[0].extra.get("logs"), Some(&json!([])));
+    assert!(fs::metadata(format!("{}/cosmoshub-4/1/10001.json.gz", dir)).is_ok());
+    assert!(archive.read(10002).unwrap().is_none());
+
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
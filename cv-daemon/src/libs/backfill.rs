@@ -0,0 +1,132 @@
+use std::fs;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use appbase::prelude::*;
+use serde_json::Value;
+
+use crate::error::error::ExpectedError;
+use crate::libs;
+use crate::libs::opt::opt_to_result;
+use crate::types::sync::{SyncState, SyncStatus};
+
+static FAILED_WRITES: AtomicU64 = AtomicU64::new(0);
+
+static REJECTED_ROWS: AtomicU64 = AtomicU64::new(0);
+
+static FINISHED: Mutex<Option<Finished>> = Mutex::new(None);
+
+struct Finished {
+  completed: bool,
+  stats: String,
+  detail: String,
+}
+
+/// the one-shot command being run, which is `backfill` or `reindex`.
+pub fn command() -> Option<String> {
+  APP.options.value_of("command").filter(|command| command == "backfill" || command == "reindex").map(|command| command.to_string())
+}
+
+/// true while a backfill or reindex job runs.
+pub fn activated() -> bool {
+  command().is_some()
+}
+
+/// true while a reindex job replays archived responses instead of requesting them.
+pub fn reindex_activated() -> bool {
+  command().as_deref() == Some("reindex")
+}
+
+/// loads `--sync` with its range replaced by `--from` and `--to`. a job never reads or writes the saved sync state.
+pub fn load_sync_state() -> Result<SyncState, ExpectedError> {
+  let command = command().unwrap_or(String::from("backfill"));
+  let sync_file = libs::opt::get_value_str("sync").map_err(|_| ExpectedError::NoneError(format!("{} needs --sync <file>!", command)))?;
+  let from_idx = libs::opt::get_value::<u64>("from").map_err(|_| ExpectedError::NoneError(format!("{} needs --from <height>!", command)))?;
+  let to_idx = libs::opt::get_value::<u64>("to").map_err(|_| ExpectedError::NoneError(format!("{} needs --to <height>!", command)))?;
+  if from_idx > to_idx {
+    return Err(ExpectedError::InvalidError(format!("{} range is empty! from={}, to={}", command, from_idx, to_idx)));
+  }
+  let json_value: Value = serde_json::from_str(fs::read_to_string(&sync_file)?.as_str())?;
+  let sync_map = opt_to_result(json_value.as_object(), format!("sync file is not object! file={}", sync_file))?;
+  SyncState::validate(sync_map)?;
+  let mut sync_state = SyncState::new(sync_map);
+  if sync_state.sync_type != "block_sync" {
+    return Err(ExpectedError::InvalidError(format!("{} only supports block_sync! sync_type={}", command, sync_state.sync_type)));
+  }
+  sync_state.from_idx = from_idx;
+  sync_state.sync_idx = from_idx;
+  sync_state.to_idx = Some(to_idx);
+  Ok(sync_state)
+}
+
+pub struct BackfillSummary {
+  started_at: Instant,
+  blocks: u64,
+  txs: u64,
+}
+
+impl BackfillSummary {
+  pub fn new() -> Self {
+    BackfillSummary { started_at: Instant::now(), blocks: 0, txs: 0 }
+  }
+
+  pub fn synced(&mut self, txs: u64) {
+    self.blocks += 1;
+    self.txs += txs;
+  }
+
+  /// keeps the summary and quits the app. it is printed by `report` once the sinks are flushed.
+  pub fn finish(&self, sync_state: &SyncState) {
+    let elapsed = self.started_at.elapsed().as_secs_f64();
+    let stats = format!("sync_id={}, from={}, to={}, synced_blocks={}, skipped_blocks={}, txs={}, elapsed={:.1}s, blocks_per_sec={:.1}",
+                        sync_state.sync_id, sync_state.from_idx, sync_state.to_idx.unwrap_or_default(),
+                        self.blocks, sync_state.sync_idx.saturating_sub(sync_state.from_idx).saturating_sub(self.blocks), self.txs,
+                        elapsed, self.blocks as f64 / elapsed.max(0.001));
+    let detail = format!("sync_idx={}, error={}", sync_state.sync_idx, sync_state.error_msg);
+    *FINISHED.lock().unwrap() = Some(Finished { completed: sync_state.status == SyncStatus::Completed, stats, detail });
+    APP.quit();
+  }
+}
+
+impl Default for BackfillSummary {
+  fn default() -> Self {
+    BackfillSummary::new()
+  }
+}
+
+/// counts a postgres write whose rows are lost, either refused or not flushed on shutdown.
+pub fn write_failed() {
+  FAILED_WRITES.fetch_add(1, Ordering::SeqCst);
+}
+
+/// counts rows that postgres rejected and moved to the dead letter table.
+pub fn rows_rejected(rows: u64) {
+  REJECTED_ROWS.fetch_add(rows, Ordering::SeqCst);
+}
+
+/// prints the summary after the app has stopped and returns the exit code, which is 1 unless the range was completed and written.
+pub fn report() -> i32 {
+  let command = command().unwrap_or(String::from("backfill"));
+  let failed_writes = FAILED_WRITES.load(Ordering::SeqCst);
+  let rejected_rows = REJECTED_ROWS.load(Ordering::SeqCst);
+  let finished = match FINISHED.lock().unwrap().take() {
+    Some(finished) => finished,
+    None => {
+      println!("{} stopped before the range was completed!", command);
+      return 1;
+    }
+  };
+  let completed = finished.completed && failed_writes == 0 && rejected_rows == 0;
+  println!("{} {}! {}", command, if completed { "completed" } else { "failed" }, finished.stats);
+  if !finished.completed {
+    println!("{}", finished.detail);
+  }
+  if failed_writes > 0 {
+    println!("postgres failed to write {} messages!", failed_writes);
+  }
+  if rejected_rows > 0 {
+    println!("postgres rejected {} rows! they are moved to cv_dead_letter.", rejected_rows);
+  }
+  if completed { 0 } else { 1 }
+}
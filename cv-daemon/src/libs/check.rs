@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fs;
+
+use serde_json::Value;
+
+use crate::error::error::ExpectedError;
+use crate::libs;
+use crate::libs::email::EmailConfig;
+use crate::libs::migration::dry_run;
+use crate::libs::opt::opt_to_result;
+use crate::libs::request;
+use crate::plugin::notifier::Notifier;
+use crate::plugin::postgres::{Pool, Postgres};
+use crate::plugin::watch::Watch;
+use crate::plugin::webhook::Webhook;
+use crate::types::cosmos::BlockResponse;
+use crate::types::postgres::PostgresSchema;
+use crate::types::sync::SyncState;
+
+pub struct CheckReport {
+  passed: usize,
+  failed: usize,
+}
+
+impl CheckReport {
+  pub fn new() -> Self {
+    CheckReport { passed: 0, failed: 0 }
+  }
+
+  /// prints the result of one check and hands its value on if it passed.
+  pub fn check<T>(&mut self, name: &str, result: Result<(String, T), ExpectedError>) -> Option<T> {
+    match result {
+      Ok((summary, value)) => {
+        self.passed += 1;
+        println!("[PASS] {}: {}", name, summary);
+        Some(value)
+      }
+      Err(err) => {
+        self.failed += 1;
+        println!("[FAIL] {}: {}", name, err.to_string().replace('\n', "\n       "));
+        None
+      }
+    }
+  }
+
+  pub fn skip(&self, name: &str, reason: &str) {
+    println!("[SKIP] {}: {}", name, reason);
+  }
+
+  pub fn finish(&self) -> i32 {
+    println!("{} checks passed, {} failed.", self.passed, self.failed);
+    if self.failed > 0 { 1 } else { 0 }
+  }
+}
+
+impl Default for CheckReport {
+  fn default() -> Self {
+    CheckReport::new()
+  }
+}
+
+/// validates the config, schemas and sync files, then connects to postgres and every endpoint. returns the exit code.
+pub fn run() -> i32 {
+  let mut report = CheckReport::new();
+  check_config(&mut report);
+
+  let schema_map = report.check("schemas", Postgres::load_schema().and_then(|schema_map| {
+    Postgres::check_sinks(&schema_map)?;
+    Ok((format!("{} schemas are valid.", schema_map.len()), schema_map))
+  }));
+  let sync_states = check_sync_files(&mut report);
+
+  let pool = report.check("postgres connection", Postgres::create_pool().and_then(|pool| {
+    let mut client = pool.get()?;
+    let version: String = client.query_one("SHOW server_version", &[])?.get(0);
+    Ok((format!("server_version={}", version), pool))
+  }));
+  match (&pool, &schema_map) {
+    (Some(pool), Some(schema_map)) => check_ddl(&mut report, pool, schema_map),
+    _ => report.skip("schema ddl", "needs valid schemas and a postgres connection."),
+  }
+
+  match tokio::runtime::Runtime::new() {
+    Ok(runtime) => runtime.block_on(check_endpoints(&mut report, &sync_states)),
+    Err(err) => {
+      let _ = report.check::<()>("endpoints", Err(ExpectedError::from(err)));
+    }
+  }
+  report.finish()
+}
+
+fn check_config(report: &mut CheckReport) {
+  let _ = report.check("config jsonrpc", libs::opt::get_value::<u16>("jsonrpc::port").map(|port| (format!("port={}", port), ())));
+  if libs::opt::get_value::<bool>("slack::activate").unwrap_or(false) {
+    let _ = report.check("config slack", ["info", "warn", "error"].iter().map(|level| {
+      libs::opt::get_value_str(format!("slack::{}", level).as_str())
+    }).collect::<Result<Vec<String>, ExpectedError>>().map(|_| (String::from("hooks are set."), ())));
+  }
+  if libs::opt::get_value::<bool>("email::activate").unwrap_or(false) {
+    let _ = report.check("config email", EmailConfig::load().map(|config| (format!("relay={}, tls={}", config.relay, config.tls), ())));
+  }
+  if libs::opt::get_value::<bool>("notifier::activate").unwrap_or(false) {
+    let _ = report.check("config notifier", Notifier::load_targets().map(|targets| (format!("{} targets are valid.", targets.len()), ())));
+  }
+  if Webhook::activated() {
+    let _ = report.check("config webhook", Webhook::load_targets().map(|targets| (format!("{} targets are valid.", targets.len()), ())));
+  }
+  if Watch::activated() {
+    let _ = report.check("config watch", Watch::load_watchlists().map(|watchlists| (format!("{} watchlists are valid.", watchlists.len()), ())));
+  }
+}
+
+fn check_sync_files(report: &mut CheckReport) -> Vec<SyncState> {
+  let mut sync_files = match fs::read_dir("sync/") {
+    Ok(dir) => dir.filter_map(|file| file.ok()).map(|file| file.path().display().to_string()).filter(|file| file.ends_with(".json")).collect::<Vec<String>>(),
+    Err(err) => {
+      let _ = report.check::<()>("sync files", Err(ExpectedError::from(err)));
+      return Vec::new();
+    }
+  };
+  sync_files.sort();
+  sync_files.iter().filter_map(|sync_file| {
+    report.check(format!("sync file {}", sync_file).as_str(), load_sync_file(sync_file).map(|sync_state| {
+      (format!("sync_id={}, endpoints={}", sync_state.sync_id, sync_state.endpoints.len()), sync_state)
+    }))
+  }).collect()
+}
+
+fn load_sync_file(sync_file: &String) -> Result<SyncState, ExpectedError> {
+  let json_value: Value = serde_json::from_str(fs::read_to_string(sync_file)?.as_str())?;
+  let sync_map = opt_to_result(json_value.as_object(), "sync file is not object!".to_string())?;
+  SyncState::validate(sync_map)?;
+  Ok(SyncState::new(sync_map))
+}
+
+fn check_ddl(report: &mut CheckReport, pool: &Pool, schema_map: &HashMap<String, PostgresSchema>) {
+  let allow_destructive = libs::opt::get_value::<bool>("postgres::allow-destructive-migration").unwrap_or(false);
+  if let Some(planned) = report.check("schema ddl", dry_run(pool, schema_map, allow_destructive).map(|planned| (String::from("every statement applies and is rolled back."), planned))) {
+    for line in planned {
+      println!("       {}", line);
+    }
+  }
+}
+
+async fn check_endpoints(report: &mut CheckReport, sync_states: &[SyncState]) {
+  for sync_state in sync_states.iter() {
+    for endpoint in sync_state.endpoints.iter() {
+      let name = format!("endpoint {} {}", sync_state.sync_type, endpoint);
+      let latest_req = libs::sync_helper::create_req_url(endpoint.clone(), String::from("blocks/latest"));
+      let result = request::get_block_typed_async::<BlockResponse>(latest_req.as_str(), "latest block").await.and_then(|latest| {
+        if latest.block.header.chain_id != sync_state.chain_id {
+          return Err(ExpectedError::InvalidError(format!("chain_id does not match! expected={}, actual={}", sync_state.chain_id, latest.block.header.chain_id)));
+        }
+        Ok((format!("chain_id={}, latest_height={}", latest.block.header.chain_id, latest.block.header.height), ()))
+      });
+      let _ = report.check(name.as_str(), result);
+    }
+  }
+}
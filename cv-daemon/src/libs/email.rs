@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::transport::smtp::PoolConfig;
+
+use crate::error::error::ExpectedError;
+use crate::libs;
+use crate::libs::notice::Notice;
+
+#[derive(Clone, Debug)]
+pub struct EmailConfig {
+  pub relay: String,
+  pub port: Option<u16>,
+  pub tls: String,
+  pub accept_invalid_certs: bool,
+  pub credentials: Option<(String, String)>,
+  pub pool_size: u32,
+  pub from: Mailbox,
+  pub reply_to: Option<Mailbox>,
+  pub recipients: HashMap<String, Vec<Mailbox>>,
+  pub subject_template: String,
+  pub body_template: String,
+}
+
+impl EmailConfig {
+  pub fn load() -> Result<Self, ExpectedError> {
+    let credentials = match (libs::opt::get_value_str("email::smtp-username"), libs::opt::get_value_str("email::smtp-password")) {
+      (Ok(username), Ok(password)) if !username.is_empty() => Some((username, password)),
+      _ => None,
+    };
+    let mut recipients = HashMap::new();
+    for level in ["info", "warn", "error"] {
+      let to = libs::opt::get_value_str(format!("email::{}-to", level).as_str()).unwrap_or_default();
+      recipients.insert(String::from(level), parse_mailboxes(to.as_str())?);
+    }
+    Ok(EmailConfig {
+      relay: libs::opt::get_value_str("email::smtp-relay")?,
+      port: libs::opt::get_value::<u16>("email::smtp-port").ok(),
+      tls: libs::opt::get_value_str("email::tls").unwrap_or(String::from("tls")),
+      accept_invalid_certs: libs::opt::get_value::<bool>("email::accept-invalid-certs").unwrap_or(false),
+      credentials,
+      pool_size: libs::opt::get_value::<u32>("email::pool-size").unwrap_or(4),
+      from: parse_mailbox(libs::opt::get_value_str("email::from")?.as_str())?,
+      reply_to: match libs::opt::get_value_str("email::reply-to") {
+        Ok(reply_to) if !reply_to.is_empty() => Some(parse_mailbox(reply_to.as_str())?),
+        _ => None,
+      },
+      recipients,
+      subject_template: libs::opt::get_value_str("email::subject-template").unwrap_or(String::from("[cv-daemon] {level}: {sync_id}")),
+      body_template: libs::opt::get_value_str("email::body-template").unwrap_or(String::from("{msg}\n\nsync_id={sync_id}\nheight={height}\nrepeated={repeated}")),
+    })
+  }
+}
+
+pub struct Mailer {
+  transport: AsyncSmtpTransport<Tokio1Executor>,
+  config: EmailConfig,
+}
+
+impl Mailer {
+  pub fn new(config: EmailConfig) -> Result<Self, ExpectedError> {
+    let tls = match config.tls.as_str() {
+      "none" => Tls::None,
+      "starttls" | "tls" => {
+        let parameters = TlsParameters::builder(config.relay.clone()).dangerous_accept_invalid_certs(config.accept_invalid_certs).build()?;
+        if config.tls == "tls" { Tls::Wrapper(parameters) } else { Tls::Required(parameters) }
+      }
+      tls => return Err(ExpectedError::InvalidError(format!("{} is not supported email tls! use tls, starttls or none.", tls))),
+    };
+    let default_port = match config.tls.as_str() {
+      "tls" => 465,
+      "starttls" => 587,
+      _ => 25,
+    };
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(config.relay.clone())
+      .port(config.port.unwrap_or(default_port))
+      .tls(tls)
+      .pool_config(PoolConfig::new().max_size(config.pool_size));
+    if let Some((username, password)) = &config.credentials {
+      builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(Mailer { transport: builder.build(), config })
+  }
+
+  pub fn has_recipients(&self, level: &str) -> bool {
+    self.config.recipients.get(level).is_some_and(|recipients| !recipients.is_empty())
+  }
+
+  pub async fn send_alert(&self, notice: &Notice) -> Result<(), ExpectedError> {
+    let recipients = self.config.recipients.get(&notice.level).cloned().unwrap_or_default();
+    if recipients.is_empty() {
+      return Ok(());
+    }
+    self.send(recipients, notice.render(self.config.subject_template.as_str()), notice.render(self.config.body_template.as_str())).await
+  }
+
+  pub async fn send(&self, to: Vec<Mailbox>, subject: String, body: String) -> Result<(), ExpectedError> {
+    let mut builder = Message::builder().from(self.config.from.clone()).subject(subject);
+    if let Some(reply_to) = &self.config.reply_to {
+      builder = builder.reply_to(reply_to.clone());
+    }
+    for mailbox in to {
+      builder = builder.to(mailbox);
+    }
+    let email = builder.body(body).map_err(|err| ExpectedError::InvalidError(format!("failed to build email! {}", err)))?;
+    let _ = self.transport.send(email).await?;
+    Ok(())
+  }
+}
+
+pub fn parse_mailbox(address: &str) -> Result<Mailbox, ExpectedError> {
+  address.trim().parse::<Mailbox>().map_err(|err| ExpectedError::InvalidError(format!("invalid email address! address={}, error={}", address, err)))
+}
+
+pub fn parse_mailboxes(addresses: &str) -> Result<Vec<Mailbox>, ExpectedError> {
+  addresses.split(',').filter(|address| !address.trim().is_empty()).map(parse_mailbox).collect()
+}
+
+#[cfg(test)]
+mod email {
+  use std::collections::HashMap;
+  use std::io::{BufRead, BufReader, Write};
+  use std::net::TcpListener;
+  use std::sync::mpsc;
+  use std::thread;
+
+  use crate::libs::email::{EmailConfig, Mailer, parse_mailbox, parse_mailboxes};
+  use crate::libs::notice::Notice;
+
+  // accepts one connection and answers just enough SMTP to take a message.
+  fn smtp_stand_in() -> (u16, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+      let (stream, _) = listener.accept().unwrap();
+      let mut writer = stream.try_clone().unwrap();
+      let mut reader = BufReader::new(stream);
+      writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+      let mut data = String::new();
+      let mut in_data = false;
+      let mut line = String::new();
+      while reader.read_line(&mut line).unwrap_or(0) > 0 {
+        if in_data {
+          if line == ".\r\n" {
+            in_data = false;
+            let _ = sender.send(data.clone());
+            writer.write_all(b"250 OK\r\n").unwrap();
+          } else {
+            data.push_str(line.as_str());
+          }
+        } else if line.starts_with("DATA") {
+          in_data = true;
+          writer.write_all(b"354 go ahead\r\n").unwrap();
+        } else if line.starts_with("QUIT") {
+          writer.write_all(b"221 bye\r\n").unwrap();
+          break;
+        } else {
+          writer.write_all(b"250 OK\r\n").unwrap();
+        }
+        line.clear();
+      }
+    });
+    (port, receiver)
+  }
+
+  #[test]
+  fn parse_mailboxes_test() {
+    assert_eq!(parse_mailboxes("ops@domain.dev, OnCall <oncall@domain.dev>").unwrap().len(), 2);
+    assert!(parse_mailboxes("").unwrap().is_empty());
+    assert!(parse_mailbox("not an address").is_err());
+  }
+
+  #[test]
+  fn send_alert_test() {
+    let (port, received) = smtp_stand_in();
+    let mut recipients = HashMap::new();
+    recipients.insert(String::from("error"), parse_mailboxes("ops@domain.dev").unwrap());
+    let config = EmailConfig {
+      relay: String::from("127.0.0.1"),
+      port: Some(port),
+      tls: String::from("none"),
+      accept_invalid_certs: false,
+      credentials: None,
+      pool_size: 1,
+      from: parse_mailbox("NoBody <nobody@domain.dev>").unwrap(),
+      reply_to: None,
+      recipients,
+      subject_template: String::from("[cv-daemon] {level}: {sync_id}"),
+      body_template: String::from("{msg} at {height}"),
+    };
+    let notice = Notice { level: String::from("error"), msg: String::from("endpoint failed!"), sync_id: Some(String::from("cosmos::cosmoshub-4::block_sync")), height: Some(7), repeated: 0 };
+
+    // the pooled transport spawns and drops its connections on the runtime.
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+      let mailer = Mailer::new(config).unwrap();
+      assert!(!mailer.has_recipients("info"));
+      mailer.send_alert(&notice).await.unwrap();
+    });
+    let data = received.recv().unwrap();
+    assert!(data.contains("Subject: [cv-daemon] error: cosmos::cosmoshub-4::block_sync"));
+    assert!(data.contains("To: ops@domain.dev"));
+    assert!(data.contains("endpoint failed! at 7"));
+  }
+}
@@ -1,19 +1,42 @@
 use appbase::prelude::*;
 
 use crate::error::error::ExpectedError;
-use crate::plugin::slack::{SlackMsg, SlackMsgLevel};
+use crate::libs::notice::Notice;
+use crate::plugin::slack::{SlackMsg, SlackMsgLevel, SlackSyncMsg};
 use crate::types::enumeration::Enumeration;
+use crate::types::sync::SyncState;
 
 pub fn error_handler(slack_sender: Sender, error: ExpectedError) {
-  log::error!("{}", error.to_string());
+  log::error!("{}", error);
   if let Err(e) = slack_sender.send(SlackMsg::new(SlackMsgLevel::Error.value(), error.to_string())) {
-    log::error!("failed to send slack message! error={}", e.to_string());
+    log::error!("failed to send slack message! error={}", e);
   }
 }
 
 pub fn warn_handler(slack_sender: Sender, error: ExpectedError) {
-  log::warn!("{}", error.to_string());
+  log::warn!("{}", error);
   if let Err(e) = slack_sender.send(SlackMsg::new(SlackMsgLevel::Warn.value(), error.to_string())) {
-    log::error!("failed to send slack message! error={}", e.to_string());
+    log::error!("failed to send slack message! error={}", e);
+  }
+}
+
+pub fn sync_error_handler(slack_sender: Sender, sync_state: &SyncState, error: ExpectedError) {
+  log::error!("{}", error);
+  if let Err(e) = slack_sender.send(SlackSyncMsg::new(SlackMsgLevel::Error.value(), error.to_string(), sync_state.sync_id.clone(), sync_state.sync_idx)) {
+    log::error!("failed to send slack message! error={}", e);
+  }
+}
+
+pub fn sync_warn_handler(slack_sender: Sender, sync_state: &SyncState, error: ExpectedError) {
+  log::warn!("{}", error);
+  if let Err(e) = slack_sender.send(SlackSyncMsg::new(SlackMsgLevel::Warn.value(), error.to_string(), sync_state.sync_id.clone(), sync_state.sync_idx)) {
+    log::error!("failed to send slack message! error={}", e);
+  }
+}
+
+pub fn sync_notice_handler(slack_sender: Sender, sync_state: &SyncState, notice: Notice) {
+  log::info!("{}", notice.msg);
+  if let Err(e) = slack_sender.send(SlackSyncMsg::new(notice.level, notice.msg, sync_state.sync_id.clone(), notice.height.unwrap_or(sync_state.sync_idx))) {
+    log::error!("failed to send slack message! error={}", e);
   }
 }
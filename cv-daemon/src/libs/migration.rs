@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use r2d2_postgres::postgres::{GenericClient, Transaction};
+
+use crate::error::error::ExpectedError;
+use crate::plugin::postgres::Pool;
+use crate::types::postgres::PostgresSchema;
+
+// the advisory lock key of schema migrations, "cv_mig" in ascii.
+const MIGRATION_LOCK: i64 = 0x63765f6d6967;
+
+const CREATE_MIGRATION_TABLE: &str = "CREATE TABLE IF NOT EXISTS cv_schema_migration (cv_schema_migration_id serial8, schema_name varchar NOT NULL, schema_version varchar NOT NULL, statements text NOT NULL, applied_at timestamptz NOT NULL DEFAULT now(), CONSTRAINT cv_schema_migration_pk PRIMARY KEY (cv_schema_migration_id))";
+
+const CREATE_DEAD_LETTER_TABLE: &str = "CREATE TABLE IF NOT EXISTS cv_dead_letter (cv_dead_letter_id serial8, schema_name varchar NOT NULL, row_values jsonb NOT NULL, error text NOT NULL, created_at timestamptz NOT NULL DEFAULT now(), CONSTRAINT cv_dead_letter_pk PRIMARY KEY (cv_dead_letter_id))";
+
+const CREATE_SYNC_STATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS sync_state (sync_type varchar NOT NULL, state jsonb NOT NULL, updated_at timestamptz NOT NULL DEFAULT now(), CONSTRAINT sync_state_pk PRIMARY KEY (sync_type))";
+
+const SELECT_COLUMNS: &str = "SELECT c.column_name::text, format_type(a.atttypid, a.atttypmod), c.is_nullable = 'YES' \
+  FROM information_schema.columns c \
+  JOIN pg_attribute a ON a.attrelid = format('%I.%I', c.table_schema, c.table_name)::regclass AND a.attname = c.column_name \
+  WHERE c.table_schema = current_schema() AND c.table_name = $1";
+
+const SELECT_INDEXES: &str = "SELECT indexname::text FROM pg_indexes WHERE schemaname = current_schema() AND tablename = $1";
+
+#[derive(Clone, Debug)]
+pub struct ExistingColumn {
+  pub name: String,
+  pub data_type: String,
+  pub nullable: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+  pub statement: String,
+  pub destructive: bool,
+}
+
+impl Migration {
+  fn additive(statement: String) -> Self {
+    Migration { statement, destructive: false }
+  }
+
+  fn destructive(statement: String) -> Self {
+    Migration { statement, destructive: true }
+  }
+}
+
+pub fn create_sync_state_table(pool: &Pool) -> Result<(), ExpectedError> {
+  let mut client = pool.get()?;
+  client.batch_execute(CREATE_SYNC_STATE_TABLE)?;
+  Ok(())
+}
+
+pub fn migrate(pool: Pool, schema_map: &HashMap<String, PostgresSchema>, allow_destructive: bool) -> Result<(), ExpectedError> {
+  let mut client = pool.get()?;
+  let mut transaction = client.transaction()?;
+  lock_migration(&mut transaction)?;
+  transaction.batch_execute(CREATE_MIGRATION_TABLE)?;
+  transaction.batch_execute(CREATE_DEAD_LETTER_TABLE)?;
+  transaction.commit()?;
+  for (_, schema) in schema_map.iter() {
+    let mut transaction = client.transaction()?;
+    lock_migration(&mut transaction)?;
+    // another daemon may have migrated the table while this one waited for the lock, so it is planned under the lock.
+    let migrations = plan_migration(&mut transaction, schema)?;
+    refuse_destructive(schema, &migrations, allow_destructive)?;
+    let applied = transaction.query_opt("SELECT 1 FROM cv_schema_migration WHERE schema_name = $1 AND schema_version = $2", &[&schema.schema_name, &schema.version])?.is_some();
+    if migrations.is_empty() && applied {
+      continue;
+    }
+
+    for migration in migrations.iter() {
+      if migration.destructive {
+        log::warn!("apply destructive migration! schema_name={}, statement={}", schema.schema_name, migration.statement);
+      } else {
+        log::info!("apply migration! schema_name={}, statement={}", schema.schema_name, migration.statement);
+      }
+      transaction.batch_execute(migration.statement.as_str())?;
+    }
+    let statements = migrations.iter().map(|migration| migration.statement.clone()).collect::<Vec<String>>().join(";\n");
+    transaction.execute("INSERT INTO cv_schema_migration (schema_name, schema_version, statements) VALUES ($1, $2, $3)", &[&schema.schema_name, &schema.version, &statements])?;
+    transaction.commit()?;
+  }
+  Ok(())
+}
+
+/// daemons that share a database migrate one at a time. the lock is released when the transaction ends.
+fn lock_migration(transaction: &mut Transaction) -> Result<(), ExpectedError> {
+  transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])?;
+  Ok(())
+}
+
+/// applies the planned migrations in a transaction which is rolled back, so the ddl is checked without changing the database.
+pub fn dry_run(pool: &Pool, schema_map: &HashMap<String, PostgresSchema>, allow_destructive: bool) -> Result<Vec<String>, ExpectedError> {
+  let mut client = pool.get()?;
+  let mut schema_names: Vec<&String> = schema_map.keys().collect();
+  schema_names.sort();
+  let mut planned = Vec::new();
+  for schema_name in schema_names {
+    let schema = schema_map.get(schema_name).unwrap();
+    let migrations = plan_migration(&mut *client, schema)?;
+    refuse_destructive(schema, &migrations, allow_destructive)?;
+    planned.push((schema, migrations));
+  }
+  let mut transaction = client.transaction()?;
+  let mut report = Vec::new();
+  for (schema, migrations) in planned {
+    for migration in migrations.iter() {
+      transaction.batch_execute(migration.statement.as_str())
+        .map_err(|err| ExpectedError::PostgresError(format!("schema_name={}, statement={}, error={}", schema.schema_name, migration.statement, err)))?;
+    }
+    let destructive = migrations.iter().filter(|migration| migration.destructive).count();
+    report.push(format!("schema_name={}, statements={}, destructive={}", schema.schema_name, migrations.len(), destructive));
+  }
+  transaction.rollback()?;
+  Ok(report)
+}
+
+fn refuse_destructive(schema: &PostgresSchema, migrations: &[Migration], allow_destructive: bool) -> Result<(), ExpectedError> {
+  let destructive: Vec<String> = migrations.iter().filter(|migration| migration.destructive).map(|migration| migration.statement.clone()).collect();
+  if !destructive.is_empty() && !allow_destructive {
+    return Err(ExpectedError::PostgresError(format!("destructive schema changes are refused! set allow-destructive-migration to apply them. schema_name={}, changes=[{}]", schema.schema_name, destructive.join("; "))));
+  }
+  Ok(())
+}
+
+fn plan_migration<C: GenericClient>(client: &mut C, schema: &PostgresSchema) -> Result<Vec<Migration>, ExpectedError> {
+  let columns: Vec<ExistingColumn> = client.query(SELECT_COLUMNS, &[&schema.schema_name])?.iter().map(|row| {
+    ExistingColumn {
+      name: row.get(0),
+      data_type: row.get(1),
+      nullable: row.get(2),
+    }
+  }).collect();
+  if columns.is_empty() {
+    return Ok(schema.create_statements().into_iter().map(Migration::additive).collect());
+  }
+  let indexes: Vec<String> = client.query(SELECT_INDEXES, &[&schema.schema_name])?.iter().map(|row| row.get(0)).collect();
+  diff_schema(schema, &columns, &indexes)
+}
+
+pub fn diff_schema(schema: &PostgresSchema, columns: &[ExistingColumn], indexes: &[String]) -> Result<Vec<Migration>, ExpectedError> {
+  let table = &schema.schema_name;
+  let system_columns = schema.system_columns();
+  let unique_names: Vec<String> = schema.uniques.iter().map(|unique_vec| PostgresSchema::unique_name(table, unique_vec, schema.versioned)).collect();
+  let index_names: Vec<String> = schema.indexes.iter().map(|index_vec| PostgresSchema::index_name(table, index_vec)).collect();
+  let mut migrations = Vec::new();
+
+  for index in indexes.iter() {
+    if index.ends_with("_cur_un") && !unique_names.contains(index) {
+      migrations.push(Migration::destructive(format!("DROP INDEX {}", index)));
+    } else if index.ends_with("_un") && !unique_names.contains(index) {
+      migrations.push(Migration::destructive(format!("ALTER TABLE {} DROP CONSTRAINT {}", table, index)));
+    } else if index.ends_with("_idx") && !index_names.contains(index) {
+      migrations.push(Migration::destructive(format!("DROP INDEX {}", index)));
+    }
+  }
+
+  for attribute in schema.attributes.iter() {
+    match columns.iter().find(|column| column.name == attribute.name) {
+      None if attribute.nullable => migrations.push(Migration::additive(format!("ALTER TABLE {} ADD COLUMN {}", table, attribute.column_definition()?))),
+      // a not null column has no value for the existing rows, so it is added nullable and tightened as a destructive step.
+      None => {
+        migrations.push(Migration::additive(format!("ALTER TABLE {} ADD COLUMN {} {} NULL", table, attribute.name, attribute.column_type()?)));
+        migrations.push(Migration::destructive(format!("ALTER TABLE {} ALTER COLUMN {} SET NOT NULL", table, attribute.name)));
+      }
+      Some(column) => {
+        let column_type = attribute.column_type()?;
+        if canonical_type(&column_type) != column.data_type {
+          migrations.push(Migration::destructive(format!("ALTER TABLE {table} ALTER COLUMN {column} TYPE {column_type} USING {column}::{column_type}", table = table, column = attribute.name, column_type = column_type)));
+        }
+        if attribute.nullable && !column.nullable {
+          migrations.push(Migration::additive(format!("ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL", table, attribute.name)));
+        } else if !attribute.nullable && column.nullable {
+          migrations.push(Migration::destructive(format!("ALTER TABLE {} ALTER COLUMN {} SET NOT NULL", table, attribute.name)));
+        }
+      }
+    }
+  }
+  for (name, definition) in system_columns.iter() {
+    if !columns.iter().any(|column| &column.name == name) {
+      migrations.push(Migration::additive(format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition)));
+    }
+  }
+
+  for column in columns.iter() {
+    if !system_columns.iter().any(|(name, _)| name == &column.name) && !schema.attributes.iter().any(|attribute| attribute.name == column.name) {
+      migrations.push(Migration::destructive(format!("ALTER TABLE {} DROP COLUMN {}", table, column.name)));
+    }
+  }
+
+  for (unique_name, unique_vec) in unique_names.iter().zip(schema.uniques.iter()) {
+    if !indexes.contains(unique_name) {
+      migrations.push(Migration::additive(schema.create_unique(unique_vec)));
+    }
+  }
+  for (index_name, create_index) in index_names.iter().zip(schema.create_index.iter()) {
+    if !indexes.contains(index_name) {
+      migrations.push(Migration::additive(create_index.clone()));
+    }
+  }
+  Ok(migrations)
+}
+
+pub fn canonical_type(column_type: &str) -> String {
+  let (base_type, array_suffix) = match column_type.strip_suffix("[]") {
+    Some(base_type) => (base_type, "[]"),
+    None => (column_type, ""),
+  };
+  let (type_name, modifier) = match base_type.find("(") {
+    Some(idx) => (&base_type[..idx], &base_type[idx..]),
+    None => (base_type, ""),
+  };
+  let type_name = match type_name {
+    "varchar" => "character varying",
+    "timestamptz" => "timestamp with time zone",
+    type_name => type_name,
+  };
+  let modifier = if type_name == "numeric" && !modifier.is_empty() && !modifier.contains(",") {
+    format!("{},0)", modifier.trim_end_matches(")"))
+  } else {
+    String::from(modifier)
+  };
+  format!("{}{}{}", type_name, modifier, array_suffix)
+}
+
+#[cfg(test)]
+mod migration {
+  use serde_json::json;
+
+  use crate::libs::migration::{canonical_type, diff_schema, ExistingColumn, Migration};
+  use crate::types::postgres::PostgresSchema;
+
+  fn existing(name: &str, data_type: &str, nullable: bool) -> ExistingColumn {
+    ExistingColumn { name: String::from(name), data_type: String::from(data_type), nullable }
+  }
+
+  #[test]
+  fn canonical_type_test() {
+    assert_eq!(canonical_type("varchar(64)[]"), "character varying(64)[]");
+    assert_eq!(canonical_type("timestamptz"), "timestamp with time zone");
+    assert_eq!(canonical_type("numeric(78)"), "numeric(78,0)");
+    assert_eq!(canonical_type("bigint"), "bigint");
+  }
+
+  #[test]
+  fn diff_schema_test() {
+    let schema = json!({
+      "attributes": {
+        "hash": { "type": [ "string", "null" ] },
+        "height": { "type": "integer" },
+        "round": { "type": "integer" },
+        "time": { "type": [ "timestamp", "null" ] }
+      },
+      "indexes": [ [ "height" ] ],
+      "uniques": [ [ "hash" ] ]
+    });
+    let schema = PostgresSchema::from(String::from("test_block"), &schema).unwrap();
+    let columns = vec!(
+      existing("test_block_id", "bigint", false),
+      existing("hash", "character varying", true),
+      existing("height", "character varying", true),
+      existing("proposer", "character varying", true),
+    );
+    let indexes = vec!(String::from("test_block_pk"), String::from("test_block_hash_un"), String::from("test_block_proposer_idx"));
+    let migrations = diff_schema(&schema, &columns, &indexes).unwrap();
+    assert_eq!(migrations, vec!(
+      Migration::destructive(String::from("DROP INDEX test_block_proposer_idx")),
+      Migration::destructive(String::from("ALTER TABLE test_block ALTER COLUMN height TYPE bigint USING height::bigint")),
+      Migration::destructive(String::from("ALTER TABLE test_block ALTER COLUMN height SET NOT NULL")),
+      Migration::additive(String::from("ALTER TABLE test_block ADD COLUMN round bigint NULL")),
+      Migration::destructive(String::from("ALTER TABLE test_block ALTER COLUMN round SET NOT NULL")),
+      Migration::additive(String::from("ALTER TABLE test_block ADD COLUMN time timestamptz NULL")),
+      Migration::destructive(String::from("ALTER TABLE test_block DROP COLUMN proposer")),
+      Migration::additive(String::from("CREATE INDEX test_block_height_idx ON test_block USING btree (height)")),
+    ));
+  }
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs::serde::{get_str, get_string, get_u64};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notice {
+  pub level: String,
+  pub msg: String,
+  pub sync_id: Option<String>,
+  pub height: Option<u64>,
+  /// how many times a digest notice was suppressed, or 0 if it is not a digest.
+  pub repeated: u64,
+}
+
+impl Notice {
+  pub fn from(params: &Map<String, Value>) -> Result<Self, ExpectedError> {
+    Ok(Notice {
+      level: get_string(params, "msg_level")?,
+      msg: get_string(params, "msg")?,
+      sync_id: get_str(params, "sync_id").ok().map(String::from),
+      height: get_u64(params, "height").ok(),
+      repeated: 0,
+    })
+  }
+
+  fn key(&self) -> String {
+    format!("{}|{}|{}", self.level, self.sync_id.clone().unwrap_or_default(), self.msg)
+  }
+
+  /// fills `{level}`, `{sync_id}`, `{height}`, `{repeated}` and `{msg}` in the template.
+  pub fn render(&self, template: &str) -> String {
+    template
+      .replace("{level}", self.level.as_str())
+      .replace("{repeated}", self.repeated.to_string().as_str())
+      .replace("{sync_id}", self.sync_id.clone().unwrap_or_default().as_str())
+      .replace("{height}", self.height.map(|height| height.to_string()).unwrap_or_default().as_str())
+      .replace("{msg}", self.msg.as_str())
+  }
+}
+
+struct Suppressed {
+  notice: Notice,
+  count: u64,
+}
+
+pub struct Throttle {
+  dedup_window: Duration,
+  rate_limits: HashMap<String, usize>,
+  last_sent: HashMap<String, Instant>,
+  level_sent: HashMap<String, VecDeque<Instant>>,
+  suppressed: BTreeMap<String, Suppressed>,
+}
+
+impl Throttle {
+  pub fn new(dedup_window: Duration, rate_limits: HashMap<String, usize>) -> Self {
+    Throttle {
+      dedup_window,
+      rate_limits,
+      last_sent: HashMap::new(),
+      level_sent: HashMap::new(),
+      suppressed: BTreeMap::new(),
+    }
+  }
+
+  /// returns true if the notice should be posted now. otherwise it is counted for the next digest.
+  pub fn check(&mut self, notice: &Notice, now: Instant) -> bool {
+    let key = notice.key();
+    if let Some(last_sent) = self.last_sent.get(&key) {
+      if now.duration_since(*last_sent) < self.dedup_window {
+        self.suppress(key, notice);
+        return false;
+      }
+    }
+    let sent = self.level_sent.entry(notice.level.clone()).or_default();
+    while sent.front().is_some_and(|sent_at| now.duration_since(*sent_at) >= RATE_WINDOW) {
+      sent.pop_front();
+    }
+    if let Some(limit) = self.rate_limits.get(&notice.level) {
+      if sent.len() >= *limit {
+        self.suppress(key, notice);
+        return false;
+      }
+    }
+    sent.push_back(now);
+    self.last_sent.insert(key, now);
+    true
+  }
+
+  fn suppress(&mut self, key: String, notice: &Notice) {
+    let suppressed = self.suppressed.entry(key).or_insert_with(|| Suppressed { notice: notice.clone(), count: 0 });
+    suppressed.count += 1;
+    if notice.height.is_some() {
+      suppressed.notice.height = notice.height;
+    }
+  }
+
+  /// drains the suppressed notices into one digest notice per message. each channel words `repeated` its own way.
+  pub fn digest(&mut self, now: Instant) -> Vec<Notice> {
+    let dedup_window = self.dedup_window;
+    self.last_sent.retain(|_, last_sent| now.duration_since(*last_sent) < dedup_window);
+    let suppressed = std::mem::take(&mut self.suppressed);
+    suppressed.into_values().map(|suppressed| {
+      let mut notice = suppressed.notice;
+      notice.repeated = suppressed.count;
+      notice
+    }).collect()
+  }
+}
+
+#[cfg(test)]
+mod notice {
+  use std::collections::HashMap;
+  use std::time::{Duration, Instant};
+
+  use crate::libs::notice::{Notice, Throttle};
+
+  fn notice(level: &str, msg: &str, height: u64) -> Notice {
+    Notice { level: String::from(level), msg: String::from(msg), sync_id: Some(String::from("cosmos::cosmoshub-4::block_sync")), height: Some(height), repeated: 0 }
+  }
+
+  #[test]
+  fn throttle_test() {
+    let mut rate_limits = HashMap::new();
+    rate_limits.insert(String::from("warn"), 2);
+    let mut throttle = Throttle::new(Duration::from_secs(600), rate_limits);
+    let now = Instant::now();
+
+    assert!(throttle.check(&notice("error", "endpoint failed", 1), now));
+    assert!(!throttle.check(&notice("error", "endpoint failed", 2), now + Duration::from_secs(1)));
+    assert!(!throttle.check(&notice("error", "endpoint failed", 3), now + Duration::from_secs(2)));
+    assert!(throttle.check(&notice("error", "endpoint failed", 4), now + Duration::from_secs(601)));
+
+    assert!(throttle.check(&notice("warn", "a", 1), now));
+    assert!(throttle.check(&notice("warn", "b", 1), now));
+    assert!(!throttle.check(&notice("warn", "c", 1), now));
+    assert!(throttle.check(&notice("warn", "c", 1), now + Duration::from_secs(60)));
+
+    let digest = throttle.digest(now + Duration::from_secs(602));
+    assert_eq!(digest, vec!(Notice { repeated: 2, ..notice("error", "endpoint failed", 3) }, Notice { repeated: 1, ..notice("warn", "c", 1) }));
+    assert!(throttle.digest(now + Duration::from_secs(603)).is_empty());
+  }
+}
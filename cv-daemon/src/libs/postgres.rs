@@ -1,11 +1,49 @@
 use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
+use primitive_types::U256;
+use r2d2_postgres::postgres::{Error as PgError, NoTls, Statement, Transaction};
+use r2d2_postgres::postgres::binary_copy::BinaryCopyInWriter;
+use r2d2_postgres::postgres::types::{ToSql, Type};
+use r2d2_postgres::PostgresConnectionManager;
+use r2d2_postgres::r2d2::PooledConnection;
 use serde_json::{Map, Value};
 
 use crate::error::error::ExpectedError;
-use crate::libs::serde::{find_value, find_value_by_path};
+use crate::libs::opt::opt_to_result;
+use crate::libs::sink::Sink;
+use crate::libs::sync_helper::save_state;
+use crate::libs::transform::resolve_value;
 use crate::plugin::postgres::Pool;
-use crate::types::postgres::PostgresSchema;
+use crate::types::postgres::{Attribute, PostgresSchema};
+
+pub const UPSERT_SYNC_STATE: &str = "INSERT INTO sync_state (sync_type, state) VALUES ($1, $2) ON CONFLICT (sync_type) DO UPDATE SET state = EXCLUDED.state, updated_at = now()";
+
+pub const SELECT_SYNC_STATE: &str = "SELECT state FROM sync_state WHERE sync_type = $1";
+
+pub const INSERT_DEAD_LETTER: &str = "INSERT INTO cv_dead_letter (schema_name, row_values, error) VALUES ($1, $2, $3)";
+
+pub type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+/// rows of a message are inserted with multi-row statements of these sizes, so a schema caches at most four statements.
+const CHUNK_SIZES: [usize; 4] = [1024, 128, 16, 1];
+
+/// postgres binds at most 65535 parameters in a statement.
+const MAX_PARAMS: usize = 65535;
+
+/// the bound parameters of a row, with the values they were created from for the dead letter table.
+pub struct Row {
+  values: Value,
+  params: Vec<SqlParam>,
+}
+
+impl Row {
+  fn param_refs(&self) -> Vec<&(dyn ToSql + Sync)> {
+    self.params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect()
+  }
+}
 
 pub fn convert_type(_type: String) -> Result<String, ExpectedError> {
   let converted = if _type == "string" {
@@ -20,107 +58,665 @@ pub fn convert_type(_type: String) -> Result<String, ExpectedError> {
     "jsonb"
   } else if _type == "array" {
     "varchar"
+  } else if _type == "timestamp" {
+    "timestamptz"
+  } else if _type == "numeric" {
+    "numeric"
+  } else if _type == "bytea" {
+    "bytea"
   } else {
-    return Err(ExpectedError::TypeError(format!("{} is not unsupported type. cv-damon only supports string, integer, number, boolean, object and array.", _type)));
+    return Err(ExpectedError::TypeError(format!("{} is not unsupported type. cv-damon only supports string, integer, number, boolean, object, array, timestamp, numeric and bytea.", _type)));
   };
   Ok(String::from(converted))
 }
 
-pub fn create_table(pool: Pool, schema_map: &HashMap<String, PostgresSchema>) -> Result<(), r2d2_postgres::postgres::Error> {
-  let mut client = pool.get().unwrap();
-  for (_, schema) in schema_map.iter() {
-    if let Err(err) = client.execute(schema.create_table.as_str(), &[]) {
-      let _ = error_handler(err)?;
+pub struct PostgresWriter {
+  pool: Pool,
+  client: Option<PooledConnection<PostgresConnectionManager<NoTls>>>,
+  statements: HashMap<(String, usize), Statement>,
+  staging_types: HashMap<String, Vec<Type>>,
+  bulk_buffer: HashMap<String, (PostgresSchema, Vec<Row>)>,
+  bulk_size: usize,
+  flush_interval: Duration,
+  last_buffered: Instant,
+  state_table: bool,
+  checkpoint: Option<(String, Value)>,
+  failed: bool,
+  kept: bool,
+  rejected: usize,
+}
+
+impl PostgresWriter {
+  pub fn new(pool: Pool, bulk_size: usize, flush_interval: Duration, state_table: bool) -> Self {
+    PostgresWriter {
+      pool,
+      client: None,
+      statements: HashMap::new(),
+      staging_types: HashMap::new(),
+      bulk_buffer: HashMap::new(),
+      bulk_size,
+      flush_interval,
+      last_buffered: Instant::now(),
+      state_table,
+      checkpoint: None,
+      failed: false,
+      kept: false,
+      rejected: 0,
     }
-    for create_index in schema.create_index.iter() {
-      if let Err(err) = client.execute(create_index.as_str(), &[]) {
-        let _ = error_handler(err)?;
+  }
+
+  fn insert_rows(&mut self, schema: &PostgresSchema, rows: Vec<Row>) -> Result<(), ExpectedError> {
+    match self.execute(schema, &rows) {
+      Ok(rejected) => {
+        self.rejected += rejected;
+        Ok(())
+      }
+      Err(err) => {
+        // the transaction is rolled back, so every row is kept and retried with the next flush.
+        self.retain_rows(schema, rows);
+        self.failed = true;
+        Err(err)
       }
     }
   }
-  Ok(())
-}
 
-pub fn error_handler(err: r2d2_postgres::postgres::Error) -> Result<(), r2d2_postgres::postgres::Error> {
-  let err_str = err.to_string();
-  if err_str.contains("already exists") {
-    log::warn!("{}", err_str);
+  fn buffer_rows(&mut self, schema: &PostgresSchema, rows: Vec<Row>) -> Result<(), ExpectedError> {
+    if self.retain_rows(schema, rows) >= self.bulk_size {
+      self.flush_all()?;
+    }
     Ok(())
-  } else {
-    log::error!("{}", err_str);
-    Err(err)
+  }
+
+  fn retain_rows(&mut self, schema: &PostgresSchema, rows: Vec<Row>) -> usize {
+    let (_, buffered) = self.bulk_buffer.entry(schema.schema_name.clone()).or_insert_with(|| (schema.clone(), Vec::new()));
+    buffered.extend(rows);
+    self.last_buffered = Instant::now();
+    buffered.len()
+  }
+
+  pub fn has_buffered(&self) -> bool {
+    self.checkpoint.is_some() || self.bulk_buffer.values().any(|(_, rows)| !rows.is_empty())
+  }
+
+  /// copies every buffered schema and the pending sync state checkpoint in one transaction.
+  /// without the state table, the checkpoint is saved to the state file once the transaction is committed.
+  /// rows that postgres rejects are moved to the dead letter table, and the buffer is only cleared once the transaction is committed,
+  /// so a flush that fails on the connection is retried as a whole.
+  pub fn flush_all(&mut self) -> Result<(), ExpectedError> {
+    if !self.has_buffered() {
+      return Ok(());
+    }
+    let buffered = std::mem::take(&mut self.bulk_buffer);
+    let checkpoint = self.checkpoint.take();
+    let result = self.try_copy(&buffered, &checkpoint);
+    self.check_connection(&result);
+    self.failed = result.is_err();
+    match result {
+      Ok(rejected) => {
+        self.rejected += rejected;
+        match checkpoint {
+          Some((sync_type, state)) if !self.state_table => save_state(sync_type.as_str(), &state),
+          _ => Ok(()),
+        }
+      }
+      Err(err) => {
+        self.bulk_buffer = buffered;
+        self.checkpoint = checkpoint;
+        Err(err)
+      }
+    }
+  }
+
+  fn try_copy(&mut self, buffered: &HashMap<String, (PostgresSchema, Vec<Row>)>, checkpoint: &Option<(String, Value)>) -> Result<usize, ExpectedError> {
+    let buffered: Vec<&(PostgresSchema, Vec<Row>)> = buffered.values().filter(|(_, rows)| !rows.is_empty()).collect();
+    if buffered.is_empty() && (checkpoint.is_none() || !self.state_table) {
+      return Ok(0);
+    }
+    let _ = self.client()?;
+    let client = self.client.as_mut().unwrap();
+    for (schema, _) in buffered.iter() {
+      if !self.staging_types.contains_key(&schema.schema_name) {
+        client.batch_execute(schema.create_staging()?.as_str())?;
+        let statement = client.prepare(format!("SELECT {} FROM {}", schema.column_names(), schema.staging_name()).as_str())?;
+        let staging_types: Vec<Type> = statement.columns().iter().map(|column| column.type_().clone()).collect();
+        self.staging_types.insert(schema.schema_name.clone(), staging_types);
+      }
+    }
+
+    let mut queries = Vec::new();
+    for (schema, _) in buffered.iter() {
+      let copy_query = format!("COPY {} ({}) FROM STDIN (FORMAT binary)", schema.staging_name(), schema.column_names());
+      queries.push((copy_query, schema.merge_staging()?, format!("TRUNCATE {}", schema.staging_name())));
+    }
+
+    let mut transaction = client.transaction()?;
+    let mut rejected = 0;
+    for ((schema, rows), (copy_query, merge_query, truncate_query)) in buffered.iter().zip(queries.iter()) {
+      log::debug!("copy buffered rows! schema_name={}, rows={}", schema.schema_name, rows.len());
+      let staging_types = &self.staging_types[&schema.schema_name];
+      rejected += write_or_reject(&mut transaction, &schema.schema_name, rows, &mut |transaction, rows| {
+        let mut copy_writer = BinaryCopyInWriter::new(transaction.copy_in(copy_query.as_str())?, staging_types);
+        for row in rows.iter() {
+          copy_writer.write(&row.param_refs())?;
+        }
+        let _ = copy_writer.finish()?;
+        let _ = transaction.execute(merge_query.as_str(), &[])?;
+        // rows are merged again in parts when one is rejected, so the staging table is emptied before the next part.
+        transaction.batch_execute(truncate_query.as_str())
+      })?;
+    }
+    if let (true, Some((sync_type, state))) = (self.state_table, checkpoint) {
+      log::debug!("checkpoint sync state! sync_type={}", sync_type);
+      let params: [&(dyn ToSql + Sync); 2] = [sync_type, state];
+      let _ = transaction.execute(UPSERT_SYNC_STATE, &params)?;
+    }
+    transaction.commit()?;
+    Ok(rejected)
+  }
+
+  fn client(&mut self) -> Result<&mut PooledConnection<PostgresConnectionManager<NoTls>>, ExpectedError> {
+    if self.client.is_none() {
+      self.client = Some(self.pool.get()?);
+      self.statements.clear();
+      self.staging_types.clear();
+    }
+    Ok(self.client.as_mut().unwrap())
+  }
+
+  fn check_connection<T>(&mut self, result: &Result<T, ExpectedError>) {
+    if result.is_err() && self.client.as_ref().is_some_and(|client| client.is_closed()) {
+      log::warn!("postgres connection is closed! reconnect on next insert.");
+      self.client = None;
+    }
+  }
+
+  fn execute(&mut self, schema: &PostgresSchema, rows: &[Row]) -> Result<usize, ExpectedError> {
+    if rows.is_empty() {
+      return Ok(0);
+    }
+    let result = self.try_execute(schema, rows);
+    self.check_connection(&result);
+    result
+  }
+
+  /// inserts the rows in a transaction with the largest prepared chunk statements that fit, and the remainder with smaller ones.
+  fn try_execute(&mut self, schema: &PostgresSchema, rows: &[Row]) -> Result<usize, ExpectedError> {
+    let _ = self.client()?;
+    let client = self.client.as_mut().unwrap();
+    let mut chunk_statements = Vec::new();
+    for chunk_size in chunk_sizes(schema.attributes.len(), rows.len()) {
+      let key = (schema.schema_name.clone(), chunk_size);
+      let statement = match self.statements.get(&key) {
+        Some(statement) => statement.clone(),
+        None => {
+          let insert_query = schema.insert_statement(chunk_size)?;
+          log::debug!("prepare insert statement! schema_name={}, rows={}", schema.schema_name, chunk_size);
+          let statement = client.prepare(insert_query.as_str())?;
+          self.statements.insert(key, statement.clone());
+          statement
+        }
+      };
+      chunk_statements.push((chunk_size, statement));
+    }
+    let mut transaction = client.transaction()?;
+    // a chunk that holds two rows of one on_conflict update target is rejected, and split until the rows are apart.
+    let rejected = write_or_reject(&mut transaction, &schema.schema_name, rows, &mut |transaction, rows| {
+      let mut start = 0;
+      while start < rows.len() {
+        let (chunk_size, statement) = chunk_statements.iter().find(|(chunk_size, _)| *chunk_size <= rows.len() - start).unwrap();
+        let params: Vec<&(dyn ToSql + Sync)> = rows[start..start + chunk_size].iter().flat_map(|row| row.param_refs()).collect();
+        let _ = transaction.execute(statement, &params)?;
+        start += chunk_size;
+      }
+      Ok(())
+    })?;
+    transaction.commit()?;
+    Ok(rejected)
   }
 }
 
-pub fn insert_value(pool: Pool, schema: &PostgresSchema, values: &mut Map<String, Value>, version: i64) -> Result<(), ExpectedError> {
-  values.insert("version".to_string(), Value::from(version));
-  let mut client = pool.get().unwrap();
-  let value_names = schema.attributes.iter().map(|attribute| { attribute.description.clone() }).collect::<Vec<String>>();
-  let insert_query = create_insert_query(&schema.insert_query, &schema.values_format, value_names, values)?;
-  log::debug!("{}", insert_query);
-  let _ = client.execute(insert_query.as_str(), &[])?;
-  Ok(())
+impl Sink for PostgresWriter {
+  fn write(&mut self, schema: &PostgresSchema, rows: &mut Vec<Value>, version: i64, height: u64, bulk: bool) -> Result<(), ExpectedError> {
+    self.kept = false;
+    let rows = create_rows(schema, rows, version, height)?;
+    // from here on, rows that fail to be written are kept and retried.
+    self.kept = true;
+    if bulk || self.state_table {
+      return self.buffer_rows(schema, rows);
+    }
+    if self.has_buffered() {
+      // rows kept by a failed flush go first, so the new rows queue behind them.
+      self.retain_rows(schema, rows);
+      return self.flush_all();
+    }
+    self.insert_rows(schema, rows)
+  }
+
+  fn flush(&mut self) -> Result<(), ExpectedError> {
+    self.flush_all()
+  }
+
+  fn checkpoint(&mut self, sync_type: &str, state: &Value, bulk: bool) -> Result<(), ExpectedError> {
+    self.checkpoint = Some((String::from(sync_type), state.clone()));
+    if bulk {
+      Ok(())
+    } else {
+      self.flush_all()
+    }
+  }
+
+  fn flush_idle(&mut self) -> Result<(), ExpectedError> {
+    if self.has_buffered() && self.last_buffered.elapsed() >= self.flush_interval {
+      self.flush_all()?;
+    }
+    Ok(())
+  }
+
+  fn failed(&self) -> bool {
+    self.failed
+  }
+
+  fn pending(&self) -> bool {
+    self.has_buffered()
+  }
+
+  fn kept(&self) -> bool {
+    self.kept
+  }
+
+  fn rejected(&mut self) -> usize {
+    std::mem::take(&mut self.rejected)
+  }
 }
 
-pub fn bulk_insert_value(pool: Pool, schema: &PostgresSchema, values_vec: &mut Vec<Value>, version: i64) -> Result<(), ExpectedError> {
-  let mut client = pool.get().unwrap();
-  let value_names = schema.attributes.iter().map(|attribute| { attribute.description.clone() }).collect::<Vec<String>>();
-  let insert_query = create_bulk_insert_query(&schema.insert_query, &schema.values_format, value_names, values_vec, version)?;
-  log::debug!("{}", insert_query);
-  let _ = client.execute(insert_query.as_str(), &[])?;
-  Ok(())
+/// the chunk sizes a statement of the schema can bind, from the largest, and no larger than the rows to insert. one row always fits.
+fn chunk_sizes(attribute_count: usize, row_count: usize) -> Vec<usize> {
+  CHUNK_SIZES.iter().cloned().filter(|chunk_size| *chunk_size == 1 || (*chunk_size <= row_count && chunk_size * attribute_count <= MAX_PARAMS)).collect()
 }
 
-fn create_insert_query(insert_query: &String, values_format: &String, value_names: Vec<String>, values: &Map<String, Value>) -> Result<String, ExpectedError> {
-  let mut values_format = values_format.clone();
-  for value_name in value_names.iter() {
-    let to = get_query_value(&values, value_name);
-    let from = format!("${}$", value_name);
-    values_format = values_format.replace(&from, &to);
+/// only connection and serialization failures are retried. a row that postgres rejects for its data,
+/// like a unique or not null violation or a failed cast, would fail the same way again.
+fn retryable(err: &PgError) -> bool {
+  match err.code() {
+    Some(state) => state.code().starts_with("08") || state.code().starts_with("40"),
+    None => err.is_closed() || err.source().is_some_and(|source| source.is::<std::io::Error>()),
   }
-  Ok(format!("{} {}", insert_query, values_format))
 }
 
-fn create_bulk_insert_query(insert_query: &String, values_format: &String, value_names: Vec<String>, values_vec: &mut Vec<Value>, version: i64) -> Result<String, ExpectedError> {
-  let mut values_parts: Vec<String> = Vec::new();
-  for raw_values in values_vec {
-    let mut temp_format = values_format.clone();
-    let values = raw_values.as_object_mut().unwrap();
-    values.insert("version".to_string(), Value::from(version));
-    for value_name in value_names.iter() {
-      let from = format!("${}$", value_name);
-      let to = get_query_value(&values, value_name);
-      temp_format = temp_format.replace(&from, &to);
+/// writes the rows in a savepoint. rows that postgres rejects are halved until each rejected row is found,
+/// and that row is moved to the dead letter table in the same transaction. returns the number of rejected rows.
+fn write_or_reject<F>(transaction: &mut Transaction, schema_name: &String, rows: &[Row], write: &mut F) -> Result<usize, PgError>
+  where F: FnMut(&mut Transaction, &[Row]) -> Result<(), PgError> {
+  let mut savepoint = transaction.transaction()?;
+  let err = match write(&mut savepoint, rows) {
+    Ok(()) => {
+      savepoint.commit()?;
+      return Ok(0);
     }
-    values_parts.push(temp_format);
+    Err(err) if retryable(&err) => return Err(err),
+    Err(err) => err,
+  };
+  savepoint.rollback()?;
+  if rows.len() > 1 {
+    let (left, right) = rows.split_at(rows.len() / 2);
+    return Ok(write_or_reject(transaction, schema_name, left, write)? + write_or_reject(transaction, schema_name, right, write)?);
   }
+  log::error!("postgres rejected a row! it is moved to cv_dead_letter. schema_name={}, error={}", schema_name, err);
+  let _ = transaction.execute(INSERT_DEAD_LETTER, &[schema_name, &rows[0].values, &err.to_string()])?;
+  Ok(1)
+}
 
-  let values_part = values_parts.join(",");
-  Ok(format!("{} {}", insert_query, values_part))
+fn create_rows(schema: &PostgresSchema, values_vec: &mut [Value], version: i64, height: u64) -> Result<Vec<Row>, ExpectedError> {
+  let mut rows = Vec::new();
+  for raw_values in values_vec.iter_mut() {
+    let values = opt_to_result(raw_values.as_object_mut(), "postgres write failed! values is not object!".to_string())?;
+    schema.system_values(values, version, height);
+    rows.push(Row { params: create_params(schema, values)?, values: raw_values.clone() });
+  }
+  Ok(rows)
 }
 
-pub fn get_query_value(values: &Map<String, Value>, target_name: &str) -> String {
-  let value = if target_name.contains(".") {
-    find_value_by_path(values, target_name)
-  } else {
-    find_value(values, target_name)
+pub fn create_params(schema: &PostgresSchema, values: &Map<String, Value>) -> Result<Vec<SqlParam>, ExpectedError> {
+  let mut params = Vec::new();
+  for attribute in schema.attributes.iter() {
+    let value = resolve_value(values, attribute)?;
+    let param = to_sql_param(value, attribute)
+      .map_err(|err| ExpectedError::TypeError(format!("failed to bind attribute! attribute={}, error={}", attribute.name, err)))?;
+    params.push(param);
+  }
+  Ok(params)
+}
+
+fn to_sql_param(value: Value, attribute: &Attribute) -> Result<SqlParam, ExpectedError> {
+  if let (true, Some(items_type)) = (attribute._type == "array", &attribute.items) {
+    let param: SqlParam = match items_type.as_str() {
+      "integer" => Box::new(nullable_array(value, to_i64)?),
+      "number" => Box::new(nullable_array(value, to_f64)?),
+      "boolean" => Box::new(nullable_array(value, to_bool)?),
+      "timestamp" => Box::new(nullable_array(value, to_timestamp)?),
+      "numeric" => Box::new(nullable_array(value, to_numeric)?),
+      "bytea" => Box::new(nullable_array(value, to_bytes)?),
+      _ => Box::new(nullable_array(value, to_text)?),
+    };
+    return Ok(param);
+  }
+  let param: SqlParam = match attribute._type.as_str() {
+    "integer" => Box::new(nullable(value, to_i64)?),
+    "number" => Box::new(nullable(value, to_f64)?),
+    "boolean" => Box::new(nullable(value, to_bool)?),
+    "timestamp" => Box::new(nullable(value, to_timestamp)?),
+    "numeric" => Box::new(nullable(value, to_numeric)?),
+    "bytea" => Box::new(nullable(value, to_bytes)?),
+    "object" => Box::new(nullable(value, |value| Ok(value.clone()))?),
+    _ => Box::new(nullable(value, to_text)?),
   };
+  Ok(param)
+}
+
+fn nullable<T, F>(value: Value, convert: F) -> Result<Option<T>, ExpectedError> where F: Fn(&Value) -> Result<T, ExpectedError> {
+  if value.is_null() {
+    Ok(None)
+  } else {
+    Ok(Some(convert(&value)?))
+  }
+}
+
+fn nullable_array<T, F>(value: Value, convert: F) -> Result<Option<Vec<T>>, ExpectedError> where F: Fn(&Value) -> Result<T, ExpectedError> {
+  match value {
+    Value::Null => Ok(None),
+    Value::Array(elements) => Ok(Some(elements.iter().map(convert).collect::<Result<Vec<T>, ExpectedError>>()?)),
+    _ => Err(ExpectedError::TypeError(format!("{} is not array!", value))),
+  }
+}
+
+fn to_text(value: &Value) -> Result<String, ExpectedError> {
+  match value {
+    Value::String(s) => Ok(s.clone()),
+    _ => Ok(value.to_string()),
+  }
+}
+
+fn to_i64(value: &Value) -> Result<i64, ExpectedError> {
+  match value {
+    Value::String(s) => Ok(s.trim().parse::<i64>()?),
+    _ => opt_to_result(value.as_i64(), format!("{} is not integer!", value)),
+  }
+}
+
+fn to_f64(value: &Value) -> Result<f64, ExpectedError> {
+  match value {
+    Value::String(s) => s.trim().parse::<f64>().map_err(|err| ExpectedError::ParsingError(err.to_string())),
+    _ => opt_to_result(value.as_f64(), format!("{} is not number!", value)),
+  }
+}
+
+fn to_bool(value: &Value) -> Result<bool, ExpectedError> {
   match value {
-    Value::Null => String::from("null"),
-    Value::String(s) => {
-      let esc = s.replace("'", "''");
-      format!("'{}'", esc)
-    },
-    Value::Array(_) => {
-      let org = value.to_string();
-      let esc = org.replace("'", "''");
-      format!("'{}'", esc)
-    },
-    Value::Object(_) => {
-      let org = value.to_string();
-      let esc = org.replace("'", "''");
-      format!("'{}'", esc)
-    },
-    _ => value.to_string(),
+    Value::String(s) => Ok(s.trim().parse::<bool>()?),
+    _ => opt_to_result(value.as_bool(), format!("{} is not boolean!", value)),
+  }
+}
+
+fn to_timestamp(value: &Value) -> Result<DateTime<Utc>, ExpectedError> {
+  let timestamp = opt_to_result(value.as_str(), format!("{} is not timestamp string!", value))?;
+  let parsed = DateTime::parse_from_rfc3339(timestamp).map_err(|err| ExpectedError::ParsingError(format!("{} is not rfc3339 format! error={}", timestamp, err)))?;
+  Ok(parsed.with_timezone(&Utc))
+}
+
+fn to_bytes(value: &Value) -> Result<Vec<u8>, ExpectedError> {
+  let hex_str = opt_to_result(value.as_str(), format!("{} is not hex string!", value))?;
+  Ok(hex::decode(hex_str.trim_start_matches("0x"))?)
+}
+
+pub fn to_numeric(value: &Value) -> Result<String, ExpectedError> {
+  let numeric = match value {
+    Value::String(s) => String::from(s.trim()),
+    Value::Number(n) => n.to_string(),
+    _ => return Err(ExpectedError::TypeError(format!("{} is not numeric!", value))),
+  };
+  let unsigned = numeric.strip_prefix("-").unwrap_or(numeric.as_str());
+  let mut parts = unsigned.splitn(2, ".");
+  let integer_part = parts.next().unwrap_or_default();
+  let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+  if !is_digits(integer_part) || !parts.next().is_none_or(is_digits) {
+    return Err(ExpectedError::TypeError(format!("{} is not numeric!", value)));
+  }
+  if U256::from_dec_str(integer_part).is_err() {
+    return Err(ExpectedError::TypeError(format!("{} is out of u256 range!", value)));
+  }
+  Ok(numeric)
+}
+
+#[cfg(test)]
+mod postgres {
+  use std::fs;
+
+  use serde_json::{json, Value};
+
+  use crate::libs::postgres::{chunk_sizes, create_params, to_numeric};
+  use crate::types::postgres::PostgresSchema;
+
+  fn test_schema() -> PostgresSchema {
+    let schema = json!({
+      "attributes": {
+        "amount": { "type": "numeric" },
+        "hash": { "type": [ "bytea", "null" ] },
+        "signers": { "type": "array", "items": { "type": "string" }, "maxLength": 64 },
+        "time": { "type": "timestamp" }
+      },
+      "indexes": [],
+      "uniques": []
+    });
+    PostgresSchema::from(String::from("test_schema"), &schema).unwrap()
+  }
+
+  #[test]
+  fn create_table_type_test() {
+    let schema = test_schema();
+    assert!(schema.create_table.contains("amount numeric NOT NULL"));
+    assert!(schema.create_table.contains("hash bytea NULL"));
+    assert!(schema.create_table.contains("signers varchar(64)[] NOT NULL"));
+    assert!(schema.create_table.contains("time timestamptz NOT NULL"));
+  }
+
+  #[test]
+  fn numeric_test() {
+    assert_eq!(to_numeric(&json!("18446744073709551616")).unwrap(), "18446744073709551616");
+    assert_eq!(to_numeric(&json!("-0.125")).unwrap(), "-0.125");
+    assert!(to_numeric(&json!("1e5")).is_err());
+    assert!(to_numeric(&json!("1.")).is_err());
+    assert!(to_numeric(&json!("1157920892373161954235709850086879078532699846656405640394575840079131296399360")).is_err());
+  }
+
+  #[test]
+  fn insert_statement_test() {
+    let schema = test_schema();
+    let statement = schema.insert_statement(2).unwrap();
+    assert_eq!(statement, "INSERT INTO test_schema (amount, hash, signers, time) VALUES ($1::text::numeric, $2, $3, $4), ($5::text::numeric, $6, $7, $8)");
+  }
+
+  #[test]
+  fn chunk_sizes_test() {
+    assert_eq!(chunk_sizes(4, 1), vec!(1));
+    assert_eq!(chunk_sizes(4, 700), vec!(128, 16, 1));
+    assert_eq!(chunk_sizes(4, 5000), vec!(1024, 128, 16, 1));
+    // 1024 rows of 100 columns would bind more than 65535 parameters.
+    assert_eq!(chunk_sizes(100, 5000), vec!(128, 16, 1));
+  }
+
+  #[test]
+  fn insert_statement_on_conflict_test() {
+    let schema = json!({
+      "attributes": {
+        "txhash": { "type": "string" },
+        "code": { "type": "integer" },
+        "raw_log": { "type": [ "string", "null" ] }
+      },
+      "indexes": [],
+      "uniques": [ [ "txhash" ] ],
+      "on_conflict": { "action": "update", "columns": [ "raw_log" ] }
+    });
+    let schema = PostgresSchema::from(String::from("test_tx"), &schema).unwrap();
+    let statement = schema.insert_statement(1).unwrap();
+    assert_eq!(statement, "INSERT INTO test_tx (code, raw_log, txhash) VALUES ($1, $2, $3) ON CONFLICT (txhash) DO UPDATE SET raw_log = EXCLUDED.raw_log");
+
+    let schema = json!({
+      "attributes": { "txhash": { "type": "string" } },
+      "indexes": [],
+      "uniques": [ [ "txhash" ] ],
+      "on_conflict": "ignore"
+    });
+    let schema = PostgresSchema::from(String::from("test_tx"), &schema).unwrap();
+    assert!(schema.insert_statement(1).unwrap().ends_with(" ON CONFLICT DO NOTHING"));
+    assert_eq!(schema.merge_staging().unwrap(), "INSERT INTO test_tx (txhash) SELECT txhash FROM test_tx_staging ON CONFLICT DO NOTHING");
+  }
+
+  #[test]
+  fn merge_staging_on_conflict_test() {
+    let schema = json!({
+      "attributes": {
+        "txhash": { "type": "string" },
+        "code": { "type": "integer" }
+      },
+      "indexes": [],
+      "uniques": [ [ "txhash" ] ],
+      "on_conflict": "update"
+    });
+    let schema = PostgresSchema::from(String::from("test_tx"), &schema).unwrap();
+    assert_eq!(schema.merge_staging().unwrap(), "INSERT INTO test_tx (code, txhash) SELECT DISTINCT ON (txhash) code, txhash FROM test_tx_staging \
+      ORDER BY txhash, test_tx_staging_id DESC ON CONFLICT (txhash) DO UPDATE SET code = EXCLUDED.code");
+    assert_eq!(schema.on_conflict.target, vec!("txhash"));
+    assert_eq!(schema.on_conflict.columns, vec!("code"));
+
+    // a bare "update" needs uniques to infer the target from.
+    let schema = json!({
+      "attributes": { "txhash": { "type": "string" } },
+      "indexes": [],
+      "uniques": [],
+      "on_conflict": "update"
+    });
+    assert!(PostgresSchema::from(String::from("test_tx"), &schema).is_err());
+  }
+
+  #[test]
+  fn insert_statement_versioned_test() {
+    let schema = json!({
+      "attributes": {
+        "operator_address": { "type": "string" },
+        "tokens": { "type": "numeric" }
+      },
+      "indexes": [],
+      "uniques": [ [ "operator_address" ] ],
+      "versioned": true
+    });
+    let schema = PostgresSchema::from(String::from("test_validator"), &schema).unwrap();
+    assert!(schema.create_table.contains("version bigint NOT NULL, valid_from bigint NOT NULL, valid_to bigint NULL"));
+    assert!(!schema.create_table.contains("UNIQUE"));
+    assert_eq!(schema.create_statements()[1], "CREATE UNIQUE INDEX test_validator_operator_address_cur_un ON test_validator USING btree (operator_address) WHERE valid_to IS NULL");
+
+    let statement = schema.insert_statement(2).unwrap();
+    assert_eq!(statement, "WITH incoming (operator_address, tokens, version, valid_from, seq) AS \
+      (VALUES ($1::varchar, $2::text::numeric, $3::bigint, $4::bigint, 1), ($5::varchar, $6::text::numeric, $7::bigint, $8::bigint, 2)), \
+      ordered AS (SELECT incoming.*, row_number() OVER (PARTITION BY incoming.operator_address ORDER BY incoming.seq) AS position, \
+      lag(ROW(incoming.tokens)) OVER (PARTITION BY incoming.operator_address ORDER BY incoming.seq) AS previous FROM incoming), \
+      changed AS (SELECT ordered.*, test_validator.version AS current_version, row_number() OVER (PARTITION BY ordered.operator_address ORDER BY ordered.seq) AS change, \
+      lead(ordered.valid_from) OVER (PARTITION BY ordered.operator_address ORDER BY ordered.seq) AS next_from \
+      FROM ordered LEFT JOIN test_validator ON test_validator.operator_address = ordered.operator_address AND test_validator.valid_to IS NULL \
+      WHERE CASE WHEN ordered.position = 1 THEN test_validator.test_validator_id IS NULL OR (test_validator.tokens) IS DISTINCT FROM (ordered.tokens) \
+      ELSE ordered.previous IS DISTINCT FROM ROW(ordered.tokens) END), \
+      closed AS (UPDATE test_validator SET valid_to = changed.valid_from FROM changed WHERE test_validator.operator_address = changed.operator_address \
+      AND changed.change = 1 AND test_validator.valid_to IS NULL RETURNING test_validator.test_validator_id) \
+      INSERT INTO test_validator (operator_address, tokens, version, valid_from, valid_to) \
+      SELECT operator_address, tokens, CASE WHEN version < 0 THEN COALESCE(current_version, 0) + change ELSE version END, valid_from, next_from \
+      FROM changed WHERE (SELECT count(*) FROM closed) >= 0");
+
+    let statement = schema.merge_staging().unwrap();
+    assert!(statement.starts_with("WITH incoming (operator_address, tokens, version, valid_from, seq) AS \
+      (SELECT operator_address, tokens::numeric, version, valid_from, test_validator_staging_id FROM test_validator_staging), "), "{}", statement);
+
+    let schema = json!({
+      "attributes": { "operator_address": { "type": "string" }, "version": { "type": "string" } },
+      "indexes": [],
+      "uniques": [ [ "operator_address" ] ],
+      "versioned": true
+    });
+    assert_eq!(PostgresSchema::validate(&String::from("test_validator"), &schema), vec!("schema=test_validator, attribute=version: attribute name is reserved!"));
+  }
+
+  #[test]
+  fn sinks_order_test() {
+    let schema = json!({
+      "attributes": { "txhash": { "type": "string" } },
+      "indexes": [],
+      "uniques": [],
+      "sinks": [ "webhook", "file", "postgres" ]
+    });
+    let schema = PostgresSchema::from(String::from("test_tx"), &schema).unwrap();
+    assert_eq!(schema.sinks, vec!("postgres", "webhook", "file"));
+  }
+
+  #[test]
+  fn staging_test() {
+    let schema = test_schema();
+    assert_eq!(schema.create_staging().unwrap(), "CREATE TEMP TABLE IF NOT EXISTS test_schema_staging (amount text, hash bytea, signers varchar(64)[], time timestamptz, test_schema_staging_id bigserial) ON COMMIT DELETE ROWS");
+    assert_eq!(schema.merge_staging().unwrap(), "INSERT INTO test_schema (amount, hash, signers, time) SELECT amount::numeric, hash, signers, time FROM test_schema_staging");
+  }
+
+  #[test]
+  fn schema_validation_test() {
+    let schema = json!({
+      "attributes": {
+        "hash": { "type": "strng" },
+        "height": { "type": "integer", "maxLength": 10 },
+        "memo": { "type": "string", "maxLength": 0, "transform": "upper" },
+        "test_block_id": { "type": "integer" }
+      },
+      "indexes": [ [ "height" ], [ "height" ] ],
+      "uniques": [ [ "proposer" ] ]
+    });
+    let problems = PostgresSchema::validate(&String::from("test_block"), &schema);
+    assert_eq!(problems, vec!(
+      "schema=test_block, attribute=hash: strng is not unsupported type. cv-damon only supports string, integer, number, boolean, object, array, timestamp, numeric and bytea.",
+      "schema=test_block, attribute=height: maxLength is not allowed for integer type!",
+      "schema=test_block, attribute=memo: maxLength must be integer between 1 and 10485760! maxLength=0",
+      "schema=test_block, attribute=memo: \"upper\" is not supported transform!",
+      "schema=test_block, attribute=test_block_id: attribute name is reserved!",
+      "schema=test_block: indexes has duplicate entry! columns=[\"height\"]",
+      "schema=test_block: uniques has unknown column! column=proposer",
+    ));
+    assert!(PostgresSchema::from(String::from("test_block"), &schema).is_err());
+
+    let schema = json!({ "attributes": { "height": { "type": "integer" } }, "indexes": [], "uniques": [] });
+    assert_eq!(PostgresSchema::validate(&String::from("sync_state"), &schema), vec!("schema=sync_state: schema name is reserved!"));
+    assert_eq!(PostgresSchema::validate(&String::from("cv_dead_letter"), &schema), vec!("schema=cv_dead_letter: schema name is reserved!"));
+  }
+
+  #[test]
+  fn schema_files_test() {
+    for file in fs::read_dir("schema/").unwrap() {
+      let json_schema: Value = serde_json::from_str(fs::read_to_string(file.unwrap().path()).unwrap().as_str()).unwrap();
+      for (schema_name, values) in json_schema.as_object().unwrap() {
+        assert_eq!(PostgresSchema::validate(schema_name, values), Vec::<String>::new());
+      }
+    }
+  }
+
+  #[test]
+  fn create_params_test() {
+    let schema = test_schema();
+    let values = json!({"amount": "340282366920938463463374607431768211456", "hash": "0xAB01", "signers": ["a'b", "c"], "time": "2021-08-19T06:41:12.531940473Z"});
+    let params = create_params(&schema, values.as_object().unwrap()).unwrap();
+    let params: Vec<String> = params.iter().map(|param| format!("{:?}", param)).collect();
+    assert_eq!(params[0], "Some(\"340282366920938463463374607431768211456\")");
+    assert_eq!(params[1], "Some([171, 1])");
+    assert_eq!(params[2], "Some([\"a'b\", \"c\"])");
+    assert_eq!(params[3], "Some(2021-08-19T06:41:12.531940473Z)");
+  }
+
+  #[test]
+  fn create_params_fail_test() {
+    let schema = test_schema();
+    let values = json!({"amount": "1.5e3", "signers": [], "time": "2021-08-19T06:41:12Z"});
+    assert!(create_params(&schema, values.as_object().unwrap()).is_err());
   }
 }
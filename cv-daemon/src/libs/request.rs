@@ -1,44 +1,78 @@
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
+use sha2::Sha256;
 
 use crate::error::error::ExpectedError;
-use crate::libs::serde::{get_string, get_u64};
+use crate::libs::serde::get_string;
+use crate::types::cosmos;
 
 pub async fn get_block_async(url: &str) -> Result<Map<String, Value>, ExpectedError> {
+  let body = get_block_body(url).await?;
+  Ok(serde_json::from_str(body.as_str())?)
+}
+
+pub async fn get_block_typed_async<T: DeserializeOwned>(url: &str, type_name: &str) -> Result<T, ExpectedError> {
+  let body = get_block_body(url).await?;
+  cosmos::decode(type_name, body.as_str())
+}
+
+/// the response body as it is received, so it can be archived before it is decoded.
+pub async fn get_block_body(url: &str) -> Result<String, ExpectedError> {
   let res = reqwest::get(url).await?;
-  let status = res.status().clone();
+  let status = res.status();
   let body = res.text().await?;
-  let parsed_body: Map<String, Value> = serde_json::from_str(body.as_str())?;
   if !status.is_success() {
-    let error = get_string(&parsed_body, "error");
-    let error_msg = if error.is_ok() {
-      error.unwrap()
-    } else {
-      "request error".to_string()
-    };
+    let parsed_body: Map<String, Value> = serde_json::from_str(body.as_str())?;
+    let error_msg = get_string(&parsed_body, "error").unwrap_or_else(|_| "request error".to_string());
     return if error_msg == "requested block height is bigger then the chain length" {
       Err(ExpectedError::BlockHeightError(error_msg))
     } else {
       Err(ExpectedError::RequestError(error_msg))
     }
   }
-  Ok(parsed_body)
+  Ok(body)
 }
 
 pub async fn get_async(url: &str) -> Result<Map<String, Value>, ExpectedError> {
+  let body = get_body(url).await?;
+  Ok(serde_json::from_str(body.as_str())?)
+}
+
+pub async fn get_typed_async<T: DeserializeOwned>(url: &str, type_name: &str) -> Result<T, ExpectedError> {
+  let body = get_body(url).await?;
+  cosmos::decode(type_name, body.as_str())
+}
+
+pub async fn get_body(url: &str) -> Result<String, ExpectedError> {
   let res = reqwest::get(url).await?;
-  let status = res.status().clone();
+  let status = res.status();
   let body = res.text().await?;
-  let parsed_body: Map<String, Value> = serde_json::from_str(body.as_str())?;
   if !status.is_success() {
-    let error = get_string(&parsed_body, "message");
-    let error_msg = if error.is_ok() {
-      error.unwrap()
-    } else {
-      "request error".to_string()
-    };
+    let parsed_body: Map<String, Value> = serde_json::from_str(body.as_str())?;
+    let error_msg = get_string(&parsed_body, "message").unwrap_or_else(|_| "request error".to_string());
     return Err(ExpectedError::RequestError(error_msg));
   }
-  Ok(parsed_body)
+  Ok(body)
+}
+
+/// the client is shared by the caller, so its connections are pooled between requests.
+pub async fn post_async(client: &reqwest::Client, url: &str, body: String, headers: Vec<(&str, String)>) -> Result<(), ExpectedError> {
+  let mut request = client.post(url).header("Content-Type", "application/json").body(body);
+  for (name, value) in headers {
+    request = request.header(name, value);
+  }
+  let res = request.send().await?;
+  if !res.status().is_success() {
+    return Err(ExpectedError::RequestError(format!("request failed! url={}, status={}", url, res.status())));
+  }
+  Ok(())
+}
+
+pub fn hmac_sha256(secret: &str, body: &str) -> Result<String, ExpectedError> {
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|err| ExpectedError::InvalidError(err.to_string()))?;
+  mac.update(body.as_bytes());
+  Ok(hex::encode(mac.finalize().into_bytes()))
 }
 
 pub fn check_slash(input_str: String) -> String {
@@ -48,3 +82,14 @@ pub fn check_slash(input_str: String) -> String {
   }
   temp_str.to_owned()
 }
+
+#[cfg(test)]
+mod request {
+  use crate::libs::request::hmac_sha256;
+
+  #[test]
+  fn hmac_sha256_test() {
+    let signature = hmac_sha256("key", "The quick brown fox jumps over the lazy dog").unwrap();
+    assert_eq!(signature, "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+  }
+}
@@ -1,5 +1,7 @@
+use std::cmp::Ordering;
 use std::str::FromStr;
 
+use primitive_types::U256;
 use serde_json::{Map, Value};
 
 use crate::error::error::ExpectedError;
@@ -39,12 +41,12 @@ pub fn find_value(values: &Map<String, Value>, target_name: &str) -> Value {
 }
 
 pub fn unwrap<'a>(params: &'a Map<String, Value>, name: &'a str) -> Result<&'a Value, ExpectedError> {
-  Ok(opt_to_result(params.get(name), format!("unwrap failed! {} is none!", name))?)
+  opt_to_result(params.get(name), format!("unwrap failed! {} is none!", name))
 }
 
 pub fn get_str<'a>(params: &'a Map<String, Value>, name: &'a str) -> Result<&'a str, ExpectedError> {
   let unwrapped = unwrap(params, name)?;
-  Ok(opt_to_result(unwrapped.as_str(), format!("unwrap failed! {} is none!", name))?)
+  opt_to_result(unwrapped.as_str(), format!("unwrap failed! {} is none!", name))
 }
 
 pub fn get_string(params: &Map<String, Value>, name: &str) -> Result<String, ExpectedError> {
@@ -54,22 +56,22 @@ pub fn get_string(params: &Map<String, Value>, name: &str) -> Result<String, Exp
 
 pub fn get_u64(params: &Map<String, Value>, name: &str) -> Result<u64, ExpectedError> {
   let unwrapped = unwrap(params, name)?;
-  Ok(opt_to_result(unwrapped.as_u64(), format!("get_u64 failed! {} is none!", name))?)
+  opt_to_result(unwrapped.as_u64(), format!("get_u64 failed! {} is none!", name))
 }
 
 pub fn get_object<'a>(params: &'a Map<String, Value>, name: &'a str) -> Result<&'a Map<String, Value>, ExpectedError> {
   let unwrapped = unwrap(params, name)?;
-  Ok(opt_to_result(unwrapped.as_object(), format!("get_object failed! {} is none!", name))?)
+  opt_to_result(unwrapped.as_object(), format!("get_object failed! {} is none!", name))
 }
 
 pub fn get_array<'a>(params: &'a Map<String, Value>, name: &'a str) -> Result<&'a Vec<Value>, ExpectedError> {
   let unwrapped = unwrap(params, name)?;
-  Ok(opt_to_result(unwrapped.as_array(), format!("get_array failed! {} is none!", name))?)
+  opt_to_result(unwrapped.as_array(), format!("get_array failed! {} is none!", name))
 }
 
 pub fn find_value_by_path(params: &Map<String, Value>, path: &str) -> Value {
   let split = path.split(".").collect::<Vec<&str>>();
-  if split.len() == 0 {
+  if split.is_empty() {
     return Value::Null;
   }
   let mut params = params;
@@ -92,6 +94,14 @@ pub fn find_value_by_path(params: &Map<String, Value>, path: &str) -> Value {
   Value::Null
 }
 
+pub fn select_value(values: &Map<String, Value>, key: &str) -> Value {
+  if key.contains(".") {
+    find_value_by_path(values, key)
+  } else {
+    find_value(values, key)
+  }
+}
+
 pub fn get_string_vec(params: &Map<String, Value>, name: &str) -> Vec<String> {
   params.get(name).unwrap().as_array().unwrap().iter().map(|item| { String::from(item.as_str().unwrap()) }).collect()
 }
@@ -148,23 +158,55 @@ pub fn filter(values: &Map<String, Value>, filter: String) -> Result<bool, Expec
   Ok(ret)
 }
 
-fn filter_value(values: &Map<String, Value>, key_value: &String) -> Result<bool, ExpectedError> {
-  let mut split_kv = key_value.split("=");
-  if split_kv.clone().count() != 2 {
-    return Err(ExpectedError::TypeError(String::from("invalid filter condition format! example='key=val'")));
+fn filter_value(values: &Map<String, Value>, key_value: &str) -> Result<bool, ExpectedError> {
+  let format_error = || ExpectedError::TypeError(String::from("invalid filter condition format! example='key=val', 'key>=100'"));
+  let is_op = |c: char| c == '=' || c == '!' || c == '>' || c == '<';
+  let (key, rest) = key_value.split_at(key_value.find(is_op).ok_or_else(format_error)?);
+  let op = ["!=", ">=", "<=", "=", ">", "<"].iter().find(|op| rest.starts_with(*op)).ok_or_else(format_error)?;
+  let key = key.trim();
+  let value = rest[op.len()..].trim();
+  if key.is_empty() || value.contains(is_op) {
+    return Err(format_error());
   }
-  let key = split_kv.next().unwrap().trim();
-  let value = split_kv.next().unwrap().trim();
-  let found = if key.contains(".") {
-    find_value_by_path(values, key)
-  } else {
-    find_value(values, key)
+  // an array matches if any of its items matches.
+  let found = match select_value(values, key) {
+    Value::Array(items) => items,
+    found => vec!(found),
   };
-  let found_val = match found {
-    Value::String(s) => s,
-    _ => found.to_string(),
-  };
-  Ok(value == found_val.as_str())
+  let mut matched = found.iter().map(|found| {
+    let found_val = match found {
+      Value::String(s) => s.clone(),
+      _ => found.to_string(),
+    };
+    match *op {
+      "=" | "!=" => value == found_val.as_str(),
+      _ => match compare_number(found_val.as_str(), value) {
+        Some(ordering) => match *op {
+          ">=" => ordering != Ordering::Less,
+          "<=" => ordering != Ordering::Greater,
+          ">" => ordering == Ordering::Greater,
+          _ => ordering == Ordering::Less,
+        },
+        None => false,
+      },
+    }
+  });
+  Ok(if *op == "!=" { !matched.any(|matched| matched) } else { matched.any(|matched| matched) })
+}
+
+// integers are compared as U256 so that large amounts keep their precision, and only fractions are compared as floats.
+fn compare_number(found: &str, value: &str) -> Option<Ordering> {
+  let (found, value) = (leading_number(found)?, leading_number(value)?);
+  if !found.contains('.') && !value.contains('.') {
+    return Some(U256::from_dec_str(found).ok()?.cmp(&U256::from_dec_str(value).ok()?));
+  }
+  found.parse::<f64>().ok()?.partial_cmp(&value.parse::<f64>().ok()?)
+}
+
+// reads the number in front of a denom, e.g. `1000uatom`.
+fn leading_number(value: &str) -> Option<&str> {
+  let end = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+  Some(&value[..end]).filter(|number| !number.is_empty())
 }
 
 fn filter_calc(bool_stack: &mut Vec<bool>, calc_stack: &mut Vec<String>) -> Result<bool, ExpectedError> {
@@ -192,7 +234,7 @@ mod serde {
     test_map.insert(String::from("key3"), json!(100));
 
     let ret = serde::filter(&test_map, String::from("(key1 = val1 & sub_key1 = sub_val1 & key3 =101) | key4=null | key3=101")).unwrap();
-    assert_eq!(ret, true);
+    assert!(ret);
   }
 
   #[test]
@@ -204,6 +246,50 @@ mod serde {
     test_map.insert(String::from("key4"), Value::String(String::from("not_null")));
 
     let ret = serde::filter(&test_map, String::from("(key1 = val1 & sub_key1 = sub_val1 & key3 =100) & key4=null")).unwrap();
-    assert_eq!(ret, false);
+    assert!(!ret);
+  }
+
+  #[test]
+  fn filter_compare_test() {
+    let test_map = json!({
+      "events": { "delegate": { "validator": [ "cosmosvaloper1a", "cosmosvaloper1b" ], "amount": [ "2500000000uatom" ] } },
+      "code": 0
+    });
+    let test_map = test_map.as_object().unwrap();
+
+    assert!(serde::filter(test_map, String::from("events.delegate.validator=cosmosvaloper1b & events.delegate.amount>=1000000000")).unwrap());
+    assert!(!serde::filter(test_map, String::from("events.delegate.amount<1000000000")).unwrap());
+    assert!(serde::filter(test_map, String::from("code!=1 & events.delegate.validator!=cosmosvaloper1c")).unwrap());
+    assert!(serde::filter(test_map, String::from("key1 val1")).is_err());
+    assert!(serde::filter(test_map, String::from("key1=val1=val2")).is_err());
+  }
+
+  #[test]
+  fn filter_number_test() {
+    let test_map = json!({
+      "amount": "100000000000000000001uatom",
+      "ratio": "0.25"
+    });
+    let test_map = test_map.as_object().unwrap();
+
+    // as floats both sides would be 1e20.
+    assert!(serde::filter(test_map, String::from("amount>100000000000000000000")).unwrap());
+    assert!(!serde::filter(test_map, String::from("amount<=100000000000000000000")).unwrap());
+    assert!(serde::filter(test_map, String::from("ratio>0.125 & ratio<1")).unwrap());
+    assert!(!serde::filter(test_map, String::from("amount>abc")).unwrap());
+  }
+
+  #[test]
+  fn filter_array_test() {
+    let test_map = json!({ "heights": [ 10, 20 ], "senders": [ "a", "b" ] });
+    let test_map = test_map.as_object().unwrap();
+
+    assert!(serde::filter(test_map, String::from("heights>15")).unwrap());
+    assert!(!serde::filter(test_map, String::from("heights>20")).unwrap());
+    assert!(serde::filter(test_map, String::from("senders=b")).unwrap());
+    assert!(!serde::filter(test_map, String::from("senders!=b")).unwrap());
+    assert!(serde::filter(test_map, String::from("senders!=c")).unwrap());
+    assert!(serde::filter(test_map, String::from("senders=a<b")).is_err());
+    assert!(serde::filter(test_map, String::from("senders!=!a")).is_err());
   }
 }
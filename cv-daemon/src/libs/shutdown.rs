@@ -0,0 +1,94 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+
+use crate::libs;
+
+static DEADLINE: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Clone)]
+pub struct ShutdownSignal {
+  stopping: Arc<watch::Sender<bool>>,
+  stopped: watch::Receiver<bool>,
+  done: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownSignal {
+  pub fn new() -> Self {
+    let (stopping, stopped) = watch::channel(false);
+    ShutdownSignal {
+      stopping: Arc::new(stopping),
+      stopped,
+      done: Arc::new((Mutex::new(false), Condvar::new())),
+    }
+  }
+
+  /// resolves once `stop_and_wait` is called, so plugin loops can select on it next to their receiver.
+  pub async fn stopped(&self) {
+    let mut stopped = self.stopped.clone();
+    while !*stopped.borrow() {
+      if stopped.changed().await.is_err() {
+        return;
+      }
+    }
+  }
+
+  pub fn is_stopped(&self) -> bool {
+    *self.stopped.borrow()
+  }
+
+  pub fn done(&self) {
+    let (lock, condvar) = &*self.done;
+    *lock.lock().unwrap() = true;
+    condvar.notify_all();
+  }
+
+  /// asks the plugin loop to stop and blocks until it calls `done` or the shutdown deadline passes.
+  pub fn stop_and_wait(&self, plugin_name: &str) -> bool {
+    let _ = self.stopping.send(true);
+    let done = self.wait(deadline().saturating_duration_since(Instant::now()));
+    if !done {
+      log::warn!("shutdown timed out! plugin={}", plugin_name);
+    }
+    done
+  }
+
+  /// blocks until the plugin loop calls `done`, e.g. after its channel is closed, or the timeout passes.
+  pub fn wait(&self, timeout: Duration) -> bool {
+    let (lock, condvar) = &*self.done;
+    let (done, _) = condvar.wait_timeout_while(lock.lock().unwrap(), timeout, |done| !*done).unwrap();
+    *done
+  }
+}
+
+impl Default for ShutdownSignal {
+  fn default() -> Self {
+    ShutdownSignal::new()
+  }
+}
+
+fn deadline() -> Instant {
+  let mut deadline = DEADLINE.lock().unwrap();
+  *deadline.get_or_insert_with(|| {
+    let timeout = libs::opt::get_value::<u64>("shutdown::timeout").unwrap_or(10000);
+    Instant::now() + Duration::from_millis(timeout)
+  })
+}
+
+#[cfg(test)]
+mod shutdown {
+  use crate::libs::shutdown::ShutdownSignal;
+
+  #[test]
+  fn stopped_test() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let signal = ShutdownSignal::new();
+    let worker = signal.clone();
+    runtime.spawn(async move {
+      worker.stopped().await;
+      worker.done();
+    });
+    assert!(signal.stop_and_wait("test"));
+  }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+
+use appbase::prelude::*;
+use serde_json::Value;
+
+use crate::error::error::ExpectedError;
+use crate::libs::opt::opt_to_result;
+use crate::libs::transform::resolve_row;
+use crate::plugin::webhook::WebhookMsg;
+use crate::types::postgres::PostgresSchema;
+
+pub trait Sink: Send {
+  fn write(&mut self, schema: &PostgresSchema, rows: &mut Vec<Value>, version: i64, height: u64, bulk: bool) -> Result<(), ExpectedError>;
+  fn flush(&mut self) -> Result<(), ExpectedError>;
+  fn flush_idle(&mut self) -> Result<(), ExpectedError>;
+
+  /// records a sync state that covers everything written so far. only the postgres sink persists it.
+  fn checkpoint(&mut self, _sync_type: &str, _state: &Value, _bulk: bool) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+
+  /// whether rows are held back by a failed write, so the sync must not move ahead of them.
+  fn failed(&self) -> bool {
+    false
+  }
+
+  /// whether the rows of the last failed write are kept for retry, so the sinks after it can still take them.
+  fn kept(&self) -> bool {
+    false
+  }
+
+  /// whether written rows are not committed yet. only the postgres sink buffers rows.
+  fn pending(&self) -> bool {
+    false
+  }
+
+  /// takes the number of rows moved to the dead letter table since the last call.
+  fn rejected(&mut self) -> usize {
+    0
+  }
+
+  /// called once every row written so far is committed to postgres.
+  fn committed(&mut self) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+}
+
+pub struct FileSink {
+  dir: String,
+  rotate_blocks: u64,
+  files: HashMap<String, (u64, BufWriter<File>)>,
+}
+
+impl FileSink {
+  pub fn new(dir: String, rotate_blocks: u64) -> Self {
+    FileSink {
+      dir,
+      rotate_blocks: rotate_blocks.max(1),
+      files: HashMap::new(),
+    }
+  }
+
+  pub fn file_path(&self, schema_name: &str, range_start: u64) -> String {
+    let range_end = range_start + self.rotate_blocks - 1;
+    format!("{}/{}/{}_{:012}_{:012}.jsonl", self.dir.trim_end_matches("/"), schema_name, schema_name, range_start, range_end)
+  }
+
+  fn writer(&mut self, schema_name: &str, height: u64) -> Result<&mut BufWriter<File>, ExpectedError> {
+    let range_start = height / self.rotate_blocks * self.rotate_blocks;
+    if let Some((current_start, _)) = self.files.get(schema_name) {
+      if *current_start != range_start {
+        let (_, mut rotated) = self.files.remove(schema_name).unwrap();
+        rotated.flush()?;
+      }
+    }
+    if !self.files.contains_key(schema_name) {
+      let file_path = self.file_path(schema_name, range_start);
+      fs::create_dir_all(format!("{}/{}", self.dir.trim_end_matches("/"), schema_name))?;
+      let file = OpenOptions::new().create(true).append(true).open(&file_path)?;
+      log::debug!("open sink file! file_path={}", file_path);
+      self.files.insert(String::from(schema_name), (range_start, BufWriter::new(file)));
+    }
+    Ok(&mut self.files.get_mut(schema_name).unwrap().1)
+  }
+}
+
+/// records of schemas that are also written to postgres are held until postgres commits them.
+pub struct WebhookSink {
+  sender: Sender,
+  pending: Vec<Value>,
+}
+
+impl WebhookSink {
+  pub fn new(sender: Sender) -> Self {
+    WebhookSink { sender, pending: Vec::new() }
+  }
+}
+
+impl Sink for WebhookSink {
+  fn write(&mut self, schema: &PostgresSchema, rows: &mut Vec<Value>, version: i64, height: u64, _: bool) -> Result<(), ExpectedError> {
+    let mut records = Vec::new();
+    for raw_values in rows.iter_mut() {
+      let values = opt_to_result(raw_values.as_object_mut(), "webhook sink write failed! values is not object!".to_string())?;
+      schema.system_values(values, version, height);
+      records.push(Value::Object(resolve_row(schema, values)?));
+    }
+    let msg = WebhookMsg::new(schema.schema_name.clone(), height, Value::Array(records));
+    if schema.sinks.iter().any(|sink| sink == "postgres") {
+      self.pending.push(msg);
+    } else {
+      let _ = self.sender.send(msg)?;
+    }
+    Ok(())
+  }
+
+  fn flush(&mut self) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+
+  fn flush_idle(&mut self) -> Result<(), ExpectedError> {
+    Ok(())
+  }
+
+  fn committed(&mut self) -> Result<(), ExpectedError> {
+    for msg in self.pending.drain(..) {
+      let _ = self.sender.send(msg)?;
+    }
+    Ok(())
+  }
+}
+
+impl Sink for FileSink {
+  fn write(&mut self, schema: &PostgresSchema, rows: &mut Vec<Value>, version: i64, height: u64, bulk: bool) -> Result<(), ExpectedError> {
+    let mut lines = Vec::new();
+    for raw_values in rows.iter_mut() {
+      let values = opt_to_result(raw_values.as_object_mut(), "file sink write failed! values is not object!".to_string())?;
+      schema.system_values(values, version, height);
+      lines.push(Value::Object(resolve_row(schema, values)?).to_string());
+    }
+    let writer = self.writer(&schema.schema_name, height)?;
+    for line in lines.iter() {
+      writeln!(writer, "{}", line)?;
+    }
+    if !bulk {
+      writer.flush()?;
+    }
+    Ok(())
+  }
+
+  fn flush(&mut self) -> Result<(), ExpectedError> {
+    for (_, (_, writer)) in self.files.iter_mut() {
+      writer.flush()?;
+    }
+    Ok(())
+  }
+
+  fn flush_idle(&mut self) -> Result<(), ExpectedError> {
+    self.flush()
+  }
+}
+
+#[cfg(test)]
+mod sink {
+  use std::fs;
+
+  use serde_json::{json, Value};
+
+  use crate::libs::sink::{FileSink, Sink};
+  use crate::types::postgres::PostgresSchema;
+
+  #[test]
+  fn file_sink_rotate_test() {
+    let schema = json!({
+      "attributes": {
+        "height": { "type": "integer", "transform": "integer" },
+        "version": { "type": "integer" }
+      },
+      "indexes": [],
+      "uniques": [],
+      "sinks": [ "file" ]
+    });
+    let schema = PostgresSchema::from(String::from("test_block"), &schema).unwrap();
+    let dir = std::env::temp_dir().join(format!("cv-file-sink-{}", std::process::id())).display().to_string();
+    let mut sink = FileSink::new(dir.clone(), 10);
+
+    sink.write(&schema, &mut vec!(json!({"height": "9"})), -1, 9, false).unwrap();
+    sink.write(&schema, &mut vec!(json!({"height": "10"}), json!({"height": "11"})), -1, 10, true).unwrap();
+    sink.flush().unwrap();
+
+    let first = fs::read_to_string(sink.file_path("test_block", 0)).unwrap();
+    assert_eq!(first, "{\"height\":9,\"version\":-1}\n");
+    let second = fs::read_to_string(sink.file_path("test_block", 10)).unwrap();
+    let lines: Vec<Value> = second.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines, vec!(json!({"height": 10, "version": -1}), json!({"height": 11, "version": -1})));
+    assert!(sink.file_path("test_block", 10).ends_with("test_block/test_block_000000000010_000000000019.jsonl"));
+    let _ = fs::remove_dir_all(dir);
+  }
+}
@@ -1,53 +1,112 @@
 use std::fs;
+use std::path::Path;
 
 use serde_json::Value;
 
 use crate::error::error::ExpectedError;
 use crate::libs;
 use crate::libs::opt::opt_to_result;
+use crate::libs::postgres::{SELECT_SYNC_STATE, UPSERT_SYNC_STATE};
 use crate::libs::request::check_slash;
 use crate::libs::serde::get_str;
+use crate::plugin::postgres::{Pool, SyncStateMsg};
 use crate::types::channel::MultiSender;
 use crate::types::enumeration::Enumeration;
 use crate::types::sync::{SyncMethod, SyncState, SyncStatus};
 
-pub fn load_sync_state(sync_type: &str) -> Result<SyncState, ExpectedError> {
+pub fn load_sync_state(sync_type: &str, pool: Option<&Pool>) -> Result<SyncState, ExpectedError> {
   log::debug!("load_sync_state; sync_type={}", sync_type);
-  let state_res = fs::read_to_string(format!("state/{}", sync_type));
-  if state_res.is_ok() {
-    let json_value: Value = serde_json::from_str(state_res.unwrap().as_str())?;
-    let sync_state_map = opt_to_result(json_value.as_object(), "load_sync_state failed! json_value is none!".to_string())?;
-    Ok(SyncState::from(sync_state_map))
-  } else {
-    log::error!("{}", state_res.err().unwrap());
-    let new_sync = fs::read_to_string(format!("sync/{}", sync_type))?;
-    let json_value: Value = serde_json::from_str(new_sync.as_str())?;
+  if let Some(pool) = pool {
+    return load_sync_state_table(sync_type, pool);
+  }
+  match fs::read_to_string(format!("state/{}", sync_type)) {
+    Ok(state) => {
+      let json_value: Value = serde_json::from_str(state.as_str())?;
+      let sync_state_map = opt_to_result(json_value.as_object(), "load_sync_state failed! json_value is none!".to_string())?;
+      Ok(SyncState::from(sync_state_map))
+    }
+    Err(err) => {
+      log::error!("{}", err);
+      let new_sync = fs::read_to_string(format!("sync/{}", sync_type))?;
+      let json_value: Value = serde_json::from_str(new_sync.as_str())?;
+      let sync_state_map = opt_to_result(json_value.as_object(), "load_sync_state failed! json_value is none!".to_string())?;
+      Ok(SyncState::new(sync_state_map))
+    }
+  }
+}
+
+fn load_sync_state_table(sync_type: &str, pool: &Pool) -> Result<SyncState, ExpectedError> {
+  let mut client = pool.get()?;
+  if let Some(row) = client.query_opt(SELECT_SYNC_STATE, &[&sync_type.trim_end_matches(".json")])? {
+    let json_value: Value = row.get(0);
     let sync_state_map = opt_to_result(json_value.as_object(), "load_sync_state failed! json_value is none!".to_string())?;
-    Ok(SyncState::new(sync_state_map))
+    return Ok(SyncState::from(sync_state_map));
+  }
+  let sync_state = load_sync_state(sync_type, None)?;
+  let _ = client.execute(UPSERT_SYNC_STATE, &[&sync_state.sync_type, &serde_json::to_value(&sync_state)?])?;
+  let state_path = format!("state/{}", sync_type);
+  if Path::new(&state_path).exists() {
+    fs::rename(&state_path, format!("{}.migrated", state_path))?;
+    log::info!("sync state file is migrated to sync_state table! file={}", state_path);
+  }
+  Ok(sync_state)
+}
+
+pub fn read_state(sync_type: &str, pool: Option<&Pool>) -> Result<Value, ExpectedError> {
+  match pool {
+    Some(pool) => {
+      let mut client = pool.get()?;
+      let row = client.query_opt(SELECT_SYNC_STATE, &[&sync_type])?;
+      let row = opt_to_result(row, format!("sync state is not found! sync_type={}", sync_type))?;
+      Ok(row.get(0))
+    }
+    None => {
+      let sync_state = fs::read_to_string(format!("state/{}.json", sync_type))?;
+      Ok(serde_json::from_str(sync_state.as_str())?)
+    }
   }
 }
 
+pub fn state_table_activated() -> bool {
+  !libs::backfill::activated() && libs::opt::get_value::<bool>("postgres::sync-state").unwrap_or(false)
+}
+
+/// sends the state to the postgres plugin, which persists it once the rows before it are committed.
+/// it is written in the same transaction with the state table, or to the state file after the commit without it.
+/// the postgres plugin pauses the sync while a flush is failing, so the state does not run ahead of rows that are not committed.
+pub fn checkpoint(sync_state: &SyncState, senders: &MultiSender, bulk: bool) -> Result<(), ExpectedError> {
+  // a backfill or reindex range is run again instead of resumed, so it keeps no state.
+  if libs::backfill::activated() {
+    return Ok(());
+  }
+  let _ = senders.get("postgres").send(SyncStateMsg::new(sync_state.sync_type.clone(), serde_json::to_value(sync_state)?, bulk))?;
+  Ok(())
+}
+
 pub fn error_handler(err: ExpectedError, sync_state: &mut SyncState, senders: &MultiSender) {
-  log::debug!("error_handler; err={}", err.to_string());
+  log::debug!("error_handler; err={}", err);
   match err {
-    ExpectedError::BlockHeightError(err) => log::debug!("{}", err.to_string()),
+    ExpectedError::BlockHeightError(err) => log::debug!("{}", err),
     ExpectedError::FilterError(err) => {
-      log::debug!("{}", err.to_string());
+      log::debug!("{}", err);
       sync_state.next_idx();
     },
-    ExpectedError::RequestError(err) => log::error!("{}", err.to_string()),
+    ExpectedError::RequestError(err) => {
+      let error = ExpectedError::RequestError(format!("endpoint={} request failed! {}", sync_state.active_node(), err));
+      libs::error::sync_warn_handler(senders.get("slack"), sync_state, error);
+    }
     _ => {
-      log::error!("{}", err.to_string());
+      log::error!("{}", err);
       sync_state.handle_error(err.to_string());
-      let _ = libs::error::error_handler(senders.get("slack"), err);
+      libs::error::sync_error_handler(senders.get("slack"), sync_state, err);
     }
   };
 }
 
-pub fn save_state(sync_state: &SyncState) -> Result<(), ExpectedError> {
-  log::debug!("sync_state; sync_id={}", sync_state.sync_id);
-  let json_str = serde_json::to_string_pretty(sync_state)?;
-  fs::write(format!("state/{}.json", sync_state.sync_type), json_str)?;
+pub fn save_state(sync_type: &str, state: &Value) -> Result<(), ExpectedError> {
+  log::debug!("save_state; sync_type={}", sync_type);
+  let json_str = serde_json::to_string_pretty(state)?;
+  fs::write(format!("state/{}.json", sync_type), json_str)?;
   Ok(())
 }
 
@@ -58,17 +117,25 @@ pub fn create_req_url(node_url: String, api: String) -> String {
 }
 
 pub fn message_handler(message: Value, sync_state: &mut SyncState) -> Result<(), ExpectedError> {
-  log::debug!("message_handler; message={}, sync_id={}", message.to_string(), sync_state.sync_id);
+  log::debug!("message_handler; message={}, sync_id={}", message, sync_state.sync_id);
   let parsed_msg = opt_to_result(message.as_object(), "message_handler failed! message is none!".to_string())?;
   let method = opt_to_result(SyncMethod::find(get_str(parsed_msg, "method")?), "message_handler failed! method is none!".to_string())?;
   match method {
+    // a paused sync waits for the postgres plugin to resume it.
+    SyncMethod::Start if sync_state.status == SyncStatus::Paused => {}
     SyncMethod::Start => {
-      sync_state.status(SyncStatus::Working);
+      sync_state.status(if sync_state.is_completed() { SyncStatus::Completed } else { SyncStatus::Working });
     }
     SyncMethod::Stop => {
       sync_state.status(SyncStatus::Stopped);
     }
+    SyncMethod::Pause if sync_state.status == SyncStatus::Working => {
+      sync_state.status(SyncStatus::Paused);
+    }
+    SyncMethod::Resume if sync_state.status == SyncStatus::Paused => {
+      sync_state.status(if sync_state.is_completed() { SyncStatus::Completed } else { SyncStatus::Working });
+    }
+    SyncMethod::Pause | SyncMethod::Resume => {}
   };
-  save_state(&sync_state)?;
   Ok(())
 }
@@ -0,0 +1,148 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs::serde::select_value;
+use crate::types::enumeration::Enumeration;
+use crate::types::postgres::{Attribute, PostgresSchema, Transform};
+
+pub fn resolve_value(values: &Map<String, Value>, attribute: &Attribute) -> Result<Value, ExpectedError> {
+  let raw_value = match &attribute.template {
+    Some(template) => render_template(values, template),
+    None => select_value(values, attribute.description.as_str()),
+  };
+  let value = match (raw_value, &attribute.default) {
+    (Value::Null, Some(default)) => default.clone(),
+    (raw_value, _) => raw_value,
+  };
+  match &attribute.transform {
+    Some(transform) => apply_transform(value, transform)
+      .map_err(|err| ExpectedError::TypeError(format!("failed to transform attribute! attribute={}, transform={}, error={}", attribute.name, transform.value(), err))),
+    None => Ok(value),
+  }
+}
+
+pub fn resolve_row(schema: &PostgresSchema, values: &Map<String, Value>) -> Result<Map<String, Value>, ExpectedError> {
+  let mut row = Map::new();
+  for attribute in schema.attributes.iter() {
+    row.insert(attribute.name.clone(), resolve_value(values, attribute)?);
+  }
+  Ok(row)
+}
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([^{}]+)\}").unwrap());
+
+pub fn render_template(values: &Map<String, Value>, template: &str) -> Value {
+  let mut rendered = String::new();
+  let mut last_end = 0;
+  for captures in PLACEHOLDER.captures_iter(template) {
+    let matched = captures.get(0).unwrap();
+    let found = match select_value(values, captures[1].trim()) {
+      Value::Null => return Value::Null,
+      Value::String(s) => s,
+      found => found.to_string(),
+    };
+    rendered.push_str(&template[last_end..matched.start()]);
+    rendered.push_str(found.as_str());
+    last_end = matched.end();
+  }
+  rendered.push_str(&template[last_end..]);
+  Value::String(rendered)
+}
+
+pub fn apply_transform(value: Value, transform: &Transform) -> Result<Value, ExpectedError> {
+  if value.is_null() {
+    return Ok(value);
+  }
+  match transform {
+    Transform::Integer => to_integer(value),
+    Transform::Timestamp => to_timestamp(value),
+    Transform::Base64 => {
+      let decoded = base64::decode(expect_str(&value)?)?;
+      Ok(Value::String(String::from_utf8(decoded)?))
+    }
+    Transform::Base64Hex => {
+      let decoded = base64::decode(expect_str(&value)?)?;
+      Ok(Value::String(hex::encode_upper(decoded)))
+    }
+    Transform::HexUpper => Ok(Value::String(hex::encode_upper(hex::decode(expect_str(&value)?)?))),
+    Transform::HexLower => Ok(Value::String(hex::encode(hex::decode(expect_str(&value)?)?))),
+  }
+}
+
+fn to_integer(value: Value) -> Result<Value, ExpectedError> {
+  match value {
+    Value::String(s) => Ok(Value::from(s.trim().parse::<i64>()?)),
+    Value::Number(n) if n.is_i64() || n.is_u64() => Ok(Value::Number(n)),
+    _ => Err(ExpectedError::TypeError(format!("{} cannot be cast to integer!", value))),
+  }
+}
+
+fn to_timestamp(value: Value) -> Result<Value, ExpectedError> {
+  let parsed = DateTime::parse_from_rfc3339(expect_str(&value)?)
+    .map_err(|err| ExpectedError::ParsingError(format!("{} is not rfc3339 format! error={}", value, err)))?;
+  Ok(Value::String(parsed.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::AutoSi, true)))
+}
+
+fn expect_str(value: &Value) -> Result<&str, ExpectedError> {
+  value.as_str().ok_or(ExpectedError::TypeError(format!("{} is not string!", value)))
+}
+
+#[cfg(test)]
+mod transform {
+  use serde_json::{json, Value};
+
+  use crate::libs::transform::{apply_transform, render_template};
+  use crate::types::postgres::Transform;
+
+  #[test]
+  fn integer_transform_test() {
+    let ret = apply_transform(json!("7135432"), &Transform::Integer).unwrap();
+    assert_eq!(ret, json!(7135432));
+  }
+
+  #[test]
+  fn integer_transform_fail_test() {
+    let ret = apply_transform(json!("12a"), &Transform::Integer);
+    assert!(ret.is_err());
+  }
+
+  #[test]
+  fn timestamp_transform_test() {
+    let ret = apply_transform(json!("2021-08-19T06:41:12.531940473+09:00"), &Transform::Timestamp).unwrap();
+    assert_eq!(ret, json!("2021-08-18T21:41:12.531940473Z"));
+  }
+
+  #[test]
+  fn base64_transform_test() {
+    let text = apply_transform(json!("Y29zbW9z"), &Transform::Base64).unwrap();
+    assert_eq!(text, json!("cosmos"));
+    let hex = apply_transform(json!("Y29zbW9z"), &Transform::Base64Hex).unwrap();
+    assert_eq!(hex, json!("636F736D6F73"));
+  }
+
+  #[test]
+  fn hex_case_transform_test() {
+    let upper = apply_transform(json!("57713bb7421c"), &Transform::HexUpper).unwrap();
+    assert_eq!(upper, json!("57713BB7421C"));
+    let lower = apply_transform(json!("57713BB7421C"), &Transform::HexLower).unwrap();
+    assert_eq!(lower, json!("57713bb7421c"));
+  }
+
+  #[test]
+  fn null_transform_test() {
+    let ret = apply_transform(Value::Null, &Transform::Timestamp).unwrap();
+    assert_eq!(ret, Value::Null);
+  }
+
+  #[test]
+  fn template_test() {
+    let values = json!({"block": {"header": {"chain_id": "cosmoshub-4", "height": "100"}}});
+    let ret = render_template(values.as_object().unwrap(), "{block.header.chain_id}-{block.header.height}");
+    assert_eq!(ret, json!("cosmoshub-4-100"));
+    let missing = render_template(values.as_object().unwrap(), "{block.header.chain_id}-{block.header.time}");
+    assert_eq!(missing, Value::Null);
+  }
+}
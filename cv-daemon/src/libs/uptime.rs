@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde_json::{json, Value};
+
+use crate::libs;
+use crate::libs::notice::Notice;
+use crate::plugin::slack::SlackMsgLevel;
+use crate::types::cosmos::Commit;
+use crate::types::enumeration::Enumeration;
+
+const BLOCK_ID_FLAG_ABSENT: u64 = 1;
+
+/// reads `block_id_flag`, which is a number from tendermint and a name from the grpc gateway.
+pub fn block_id_flag(flag: &Value) -> u64 {
+  match flag {
+    Value::Number(flag) => flag.as_u64().unwrap_or(BLOCK_ID_FLAG_ABSENT),
+    Value::String(flag) => match flag.as_str() {
+      "BLOCK_ID_FLAG_COMMIT" => 2,
+      "BLOCK_ID_FLAG_NIL" => 3,
+      flag => flag.parse::<u64>().unwrap_or(BLOCK_ID_FLAG_ABSENT),
+    },
+    _ => BLOCK_ID_FLAG_ABSENT,
+  }
+}
+
+/// returns the validators which signed the committed block. a nil vote counts as signed, as in slashing.
+pub fn signed_validators(commit: &Commit) -> HashSet<String> {
+  commit.signatures.iter()
+    .filter(|sig| block_id_flag(&sig.block_id_flag) != BLOCK_ID_FLAG_ABSENT)
+    .filter_map(|sig| sig.validator_address.clone())
+    .filter(|address| !address.is_empty())
+    .map(|address| address.to_uppercase())
+    .collect()
+}
+
+/// one row per signature with an address, and a missed row for each tracked validator which did not sign.
+pub fn commit_rows(commit: &Commit, validators: &[String]) -> Vec<Value> {
+  let mut rows: Vec<Value> = commit.signatures.iter().filter_map(|sig| {
+    let address = sig.validator_address.clone().filter(|address| !address.is_empty())?;
+    let flag = block_id_flag(&sig.block_id_flag);
+    Some(json!({
+      "height": commit.height,
+      "validator_address": address.to_uppercase(),
+      "block_id_flag": flag,
+      "signed": flag != BLOCK_ID_FLAG_ABSENT,
+      "timestamp": sig.timestamp,
+    }))
+  }).collect();
+  let signed = signed_validators(commit);
+  for validator in validators.iter().filter(|validator| !signed.contains(*validator)) {
+    rows.push(json!({
+      "height": commit.height,
+      "validator_address": validator,
+      "block_id_flag": BLOCK_ID_FLAG_ABSENT,
+      "signed": false,
+      "timestamp": Value::Null,
+    }));
+  }
+  rows
+}
+
+pub struct UptimeTracker {
+  validators: Vec<String>,
+  window: usize,
+  max_missed: usize,
+  blocks: HashMap<String, VecDeque<bool>>,
+  total_missed: HashMap<String, u64>,
+  alerting: HashSet<String>,
+  last_height: u64,
+}
+
+impl UptimeTracker {
+  pub fn load() -> Option<Self> {
+    if !libs::opt::get_value::<bool>("uptime::activate").unwrap_or(false) {
+      return None;
+    }
+    let validators = libs::opt::get_value_str("uptime::validators").unwrap_or_default();
+    let validators = validators.split(',').map(|validator| validator.trim().to_uppercase()).filter(|validator| !validator.is_empty()).collect();
+    let window = libs::opt::get_value::<usize>("uptime::window").unwrap_or(100);
+    let max_missed = libs::opt::get_value::<usize>("uptime::max-missed").unwrap_or(5);
+    Some(Self::new(validators, window, max_missed))
+  }
+
+  pub fn new(validators: Vec<String>, window: usize, max_missed: usize) -> Self {
+    UptimeTracker {
+      validators,
+      window: window.max(1),
+      max_missed: max_missed.max(1),
+      blocks: HashMap::new(),
+      total_missed: HashMap::new(),
+      alerting: HashSet::new(),
+      last_height: 0,
+    }
+  }
+
+  pub fn validators(&self) -> &Vec<String> {
+    &self.validators
+  }
+
+  /// counts the commit into the rolling window. returns the alerts and resolves, or none if the height was already counted.
+  pub fn record(&mut self, commit: &Commit) -> Option<Vec<Notice>> {
+    if commit.height <= self.last_height {
+      return None;
+    }
+    self.last_height = commit.height;
+    let signed = signed_validators(commit);
+    let mut notices = Vec::new();
+    for validator in self.validators.iter() {
+      let blocks = self.blocks.entry(validator.clone()).or_default();
+      let validator_signed = signed.contains(validator);
+      blocks.push_back(validator_signed);
+      if blocks.len() > self.window {
+        blocks.pop_front();
+      }
+      if !validator_signed {
+        *self.total_missed.entry(validator.clone()).or_insert(0) += 1;
+      }
+      let missed = blocks.iter().filter(|signed| !**signed).count();
+      if missed >= self.max_missed && self.alerting.insert(validator.clone()) {
+        notices.push(Self::notice(SlackMsgLevel::Error, format!("[ALERT] validator={} missed {} of the last {} blocks!", validator, missed, blocks.len()), commit.height));
+      } else if missed < self.max_missed && self.alerting.remove(validator) {
+        notices.push(Self::notice(SlackMsgLevel::Info, format!("[RESOLVED] validator={} missed {} of the last {} blocks.", validator, missed, blocks.len()), commit.height));
+      }
+    }
+    Some(notices)
+  }
+
+  fn notice(level: SlackMsgLevel, msg: String, height: u64) -> Notice {
+    Notice { level: level.value(), msg, sync_id: None, height: Some(height), repeated: 0 }
+  }
+
+  pub fn rows(&self) -> Vec<Value> {
+    self.validators.iter().filter_map(|validator| {
+      let blocks = self.blocks.get(validator)?;
+      let signed = blocks.iter().filter(|signed| **signed).count();
+      Some(json!({
+        "validator_address": validator,
+        "window_size": blocks.len(),
+        "signed_blocks": signed,
+        "missed_blocks": blocks.len() - signed,
+        "uptime": signed as f64 / blocks.len() as f64,
+        "total_missed": self.total_missed.get(validator).cloned().unwrap_or_default(),
+        "last_height": self.last_height,
+      }))
+    }).collect()
+  }
+}
+
+#[cfg(test)]
+mod uptime {
+  use serde_json::json;
+
+  use crate::libs::uptime::{commit_rows, UptimeTracker};
+  use crate::types::cosmos::Commit;
+
+  fn commit(height: u64, signers: Vec<&str>) -> Commit {
+    let mut signatures: Vec<_> = signers.iter().map(|signer| json!({"block_id_flag": "BLOCK_ID_FLAG_COMMIT", "validator_address": signer, "timestamp": "2021-10-01T00:00:00Z", "signature": "c2ln"})).collect();
+    signatures.push(json!({"block_id_flag": 1, "validator_address": "", "timestamp": "0001-01-01T00:00:00Z", "signature": null}));
+    serde_json::from_value(json!({"height": height.to_string(), "round": 0, "block_id": {"hash": "AB"}, "signatures": signatures})).unwrap()
+  }
+
+  #[test]
+  fn record_test() {
+    let mut tracker = UptimeTracker::new(vec!(String::from("AAAA")), 4, 2);
+
+    assert!(tracker.record(&commit(1, vec!("aaaa", "BBBB"))).unwrap().is_empty());
+    assert!(tracker.record(&commit(2, vec!("BBBB"))).unwrap().is_empty());
+    assert!(tracker.record(&commit(2, vec!("BBBB"))).is_none());
+    let alerts = tracker.record(&commit(3, vec!("BBBB"))).unwrap();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].msg.starts_with("[ALERT] validator=AAAA missed 2 of the last 3 blocks!"));
+    assert!(tracker.record(&commit(4, vec!("AAAA"))).unwrap().is_empty());
+    assert!(tracker.record(&commit(5, vec!("AAAA"))).unwrap().is_empty());
+    assert_eq!(tracker.record(&commit(6, vec!("AAAA"))).unwrap()[0].level, "info");
+
+    assert_eq!(tracker.rows(), vec!(json!({
+      "validator_address": "AAAA", "window_size": 4, "signed_blocks": 3, "missed_blocks": 1, "uptime": 0.75, "total_missed": 2, "last_height": 6
+    })));
+    let rows = commit_rows(&commit(7, vec!("BBBB")), tracker.validators());
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1]["validator_address"], "AAAA");
+    assert_eq!(rows[1]["signed"], false);
+  }
+}
@@ -1,20 +1,48 @@
 use appbase::prelude::*;
 
-use crate::plugin::block_sync::BlockSync;
-use crate::plugin::sync_manager::SyncManager;
-
-mod plugin;
-mod types;
-mod libs;
-mod error;
+use cv_daemon::libs;
+use cv_daemon::plugin::block_sync::BlockSync;
+use cv_daemon::plugin::postgres::Postgres;
+use cv_daemon::plugin::sync_manager::SyncManager;
+use cv_daemon::plugin::watch::Watch;
 
 fn main() {
   env_logger::init();
   APP.register::<BlockSync>();
   APP.register::<SyncManager>();
+  APP.register::<Watch>();
+  APP.options.arg(clap::Arg::new("command").index(1).possible_values(&["check", "backfill", "reindex"]));
+  APP.options.arg(clap::Arg::new("sync").long("sync").takes_value(true));
+  APP.options.arg(clap::Arg::new("from").long("from").takes_value(true));
+  APP.options.arg(clap::Arg::new("to").long("to").takes_value(true));
+  APP.options.arg(clap::Arg::new("schemas").long("schemas").takes_value(true));
+  APP.options.arg(clap::Arg::new("validate-schema").long("validate-schema"));
+  APP.options.arg(clap::Arg::new("shutdown::timeout").long("shutdown-timeout").takes_value(true));
   APP.init();
+  if APP.options.value_of("command").as_deref() == Some("check") {
+    std::process::exit(libs::check::run());
+  }
+  if APP.options.is_present("validate-schema") {
+    match Postgres::load_schema() {
+      Ok(schema_map) => {
+        println!("{} schemas are valid.", schema_map.len());
+        std::process::exit(0);
+      }
+      Err(err) => {
+        eprintln!("{}", err);
+        std::process::exit(1);
+      }
+    }
+  }
   APP.plugin_init::<BlockSync>();
-  APP.plugin_init::<SyncManager>();
+  // a backfill or reindex job runs without json-rpc, so parallel jobs do not share a port.
+  if !libs::backfill::activated() {
+    APP.plugin_init::<SyncManager>();
+    APP.plugin_init::<Watch>();
+  }
   APP.startup();
   APP.execute();
+  if libs::backfill::activated() {
+    std::process::exit(libs::backfill::report());
+  }
 }
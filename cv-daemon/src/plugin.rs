@@ -1,6 +1,10 @@
+pub mod alert;
 pub mod jsonrpc;
 pub mod email;
 pub mod slack;
+pub mod notifier;
 pub mod postgres;
+pub mod webhook;
+pub mod watch;
 pub mod block_sync;
 pub mod sync_manager;
@@ -0,0 +1,117 @@
+use std::time::{Duration, Instant};
+
+use appbase::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{enumeration, libs, message};
+use crate::libs::alert::{AlertEvaluator, AlertRules, SyncHealth};
+use crate::libs::serde::get_u64;
+use crate::libs::shutdown::ShutdownSignal;
+use crate::libs::notice::Notice;
+use crate::plugin::slack::{SlackMsg, SlackSyncMsg};
+use crate::types::channel::MultiSender;
+use crate::types::enumeration::Enumeration;
+
+#[appbase_plugin]
+pub struct Alert {
+  rules: Option<AlertRules>,
+  senders: Option<MultiSender>,
+  monitor: Option<Receiver>,
+  shutdown: ShutdownSignal,
+}
+
+message!(SyncHealthMsg; {sync_id: String}, {sync_idx: u64}, {chain_head: u64}, {endpoint: String}, {endpoint_idx: u64}, {status: String}, {error_msg: String});
+message!(InsertStatMsg; {writes: u64}, {errors: u64});
+enumeration!(AlertRule; {Lag: "lag"}, {Stall: "stall"}, {Failover: "failover"}, {InsertErrorRate: "insert_error_rate"}, {SyncError: "sync_error"});
+
+impl Plugin for Alert {
+  fn new() -> Self {
+    APP.options.arg(clap::Arg::new("alert::activate").long("alert-activate").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::interval").long("alert-interval").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::lag-blocks").long("alert-lag-blocks").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::stall-minutes").long("alert-stall-minutes").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::failover").long("alert-failover").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::insert-error-rate").long("alert-insert-error-rate").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::insert-error-window").long("alert-insert-error-window").takes_value(true));
+    APP.options.arg(clap::Arg::new("alert::sync-error").long("alert-sync-error").takes_value(true));
+
+    Alert {
+      rules: None,
+      senders: None,
+      monitor: None,
+      shutdown: ShutdownSignal::new(),
+    }
+  }
+
+  fn init(&mut self) {
+    self.rules = Some(AlertRules::load());
+    self.senders = Some(MultiSender::new(vec!("slack")));
+    self.monitor = Some(APP.channels.subscribe("alert"));
+  }
+
+  fn startup(&mut self) {
+    let rules = self.rules.take().unwrap();
+    let senders = self.senders.take().unwrap();
+    let monitor = self.monitor.take().unwrap();
+    Self::recv(rules, senders, monitor, self.shutdown.clone());
+  }
+
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("alert");
+  }
+}
+
+impl Alert {
+  fn recv(rules: AlertRules, senders: MultiSender, mut monitor: Receiver, shutdown: ShutdownSignal) {
+    APP.spawn(async move {
+      let mut evaluator = AlertEvaluator::new(rules);
+      let interval = Duration::from_millis(libs::opt::get_value::<u64>("alert::interval").unwrap_or(30000).max(1));
+      let mut evaluate_tick = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => Self::observe(&mut evaluator, msg),
+            Err(RecvError::Lagged(skipped)) => log::warn!("alert channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = evaluate_tick.tick() => {
+            for notice in evaluator.evaluate(Instant::now()) {
+              Self::notify(&senders, notice);
+            }
+          },
+          _ = shutdown.stopped() => break,
+        }
+      }
+      shutdown.done();
+    });
+  }
+
+  fn observe(evaluator: &mut AlertEvaluator, msg: Value) {
+    let parsed_msg = msg.as_object().unwrap();
+    if parsed_msg.contains_key("writes") {
+      match (get_u64(parsed_msg, "writes"), get_u64(parsed_msg, "errors")) {
+        (Ok(writes), Ok(errors)) => evaluator.observe_inserts(writes, errors, Instant::now()),
+        _ => log::error!("invalid insert stat message! msg={}", msg),
+      }
+    } else {
+      match SyncHealth::from(parsed_msg) {
+        Ok(health) => evaluator.observe_sync(health, Instant::now()),
+        Err(err) => log::error!("invalid sync health message! error={}", err),
+      }
+    }
+  }
+
+  fn notify(senders: &MultiSender, notice: Notice) {
+    log::info!("{}", notice.msg);
+    let msg = match (notice.sync_id, notice.height) {
+      (Some(sync_id), Some(height)) => SlackSyncMsg::new(notice.level, notice.msg, sync_id, height),
+      _ => SlackMsg::new(notice.level, notice.msg),
+    };
+    if let Err(err) = senders.get("slack").send(msg) {
+      log::error!("failed to send alert! error={}", err);
+    }
+  }
+}
@@ -1,104 +1,271 @@
 use appbase::prelude::*;
 use clap::Arg;
 use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::error::error::ExpectedError;
 use crate::libs;
+use crate::libs::alert::HealthReporter;
+use crate::libs::archive::Archive;
+use crate::libs::backfill;
+use crate::libs::backfill::BackfillSummary;
 use crate::libs::request;
-use crate::libs::serde::{filter, find_value_by_path, get_array};
+use crate::libs::serde::filter;
+use crate::libs::shutdown::ShutdownSignal;
 use crate::libs::sync_helper::load_sync_state;
+use crate::libs::uptime::{commit_rows, UptimeTracker};
 use crate::plugin::postgres::{Postgres, PostgresMsg};
+use crate::plugin::slack::{SlackMsgLevel, SlackSyncMsg};
 use crate::types::channel::MultiSender;
-use crate::types::sync::SyncState;
+use crate::types::cosmos;
+use crate::types::cosmos::{BlockResponse, TxResponse};
+use crate::types::enumeration::Enumeration;
+use crate::types::sync::{SyncState, SyncStatus};
+
+const HEAD_REFRESH_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(10);
 
 #[appbase_plugin(Postgres)]
 pub struct BlockSync {
   sync_state: Option<SyncState>,
   senders: Option<MultiSender>,
   receiver: Option<Receiver>,
+  shutdown: ShutdownSignal,
 }
 
 impl Plugin for BlockSync {
   fn new() -> Self {
     APP.options.arg(Arg::new("block::poll-interval").long("block-poll-interval").takes_value(true));
+    APP.options.arg(Arg::new("block::bulk-lag").long("block-bulk-lag").takes_value(true));
+    APP.options.arg(Arg::new("uptime::activate").long("uptime-activate").takes_value(true));
+    APP.options.arg(Arg::new("uptime::validators").long("uptime-validators").takes_value(true));
+    APP.options.arg(Arg::new("uptime::window").long("uptime-window").takes_value(true));
+    APP.options.arg(Arg::new("uptime::max-missed").long("uptime-max-missed").takes_value(true));
+    APP.options.arg(Arg::new("archive::activate").long("archive-activate").takes_value(true));
+    APP.options.arg(Arg::new("archive::dir").long("archive-dir").takes_value(true));
     BlockSync {
       sync_state: None,
       senders: None,
       receiver: None,
+      shutdown: ShutdownSignal::new(),
     }
   }
 
   fn init(&mut self) {
-    self.senders = Some(MultiSender::new(vec!("postgres", "slack")));
+    self.senders = Some(MultiSender::new(vec!("postgres", "slack", "alert")));
     self.receiver = Some(APP.channels.subscribe("block_sync"));
-    self.sync_state = Some(load_sync_state("block_sync.json").unwrap());
+    let pool = if libs::sync_helper::state_table_activated() {
+      Some(APP.run_with::<Postgres, _, _>(|postgres| postgres.pool()))
+    } else {
+      None
+    };
+    let sync_state = if backfill::activated() {
+      backfill::load_sync_state()
+    } else {
+      load_sync_state("block_sync.json", pool.as_ref())
+    };
+    match sync_state {
+      Ok(sync_state) => self.sync_state = Some(sync_state),
+      Err(err) => {
+        log::error!("failed to load sync state!\n{}", err);
+        std::process::exit(1);
+      }
+    }
   }
 
   fn startup(&mut self) {
     let receiver = self.receiver.take().unwrap();
     let sync_state = self.sync_state.take().unwrap();
     let senders = self.senders.take().unwrap();
-    let app = APP.quit_handle().unwrap();
 
-    Self::process(receiver, sync_state, senders, app);
+    Self::process(receiver, sync_state, senders, self.shutdown.clone());
   }
 
-  fn shutdown(&mut self) {}
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("block_sync");
+  }
 }
 
 impl BlockSync {
-  fn process(mut receiver: Receiver, mut sync_state: SyncState, senders: MultiSender, app: QuitHandle) {
+  fn process(mut receiver: Receiver, mut sync_state: SyncState, senders: MultiSender, shutdown: ShutdownSignal) {
     APP.spawn(async move {
-      log::debug!("start to block sync process");
-      if let Ok(msg) = receiver.try_recv() {
-        if let Err(err) = libs::sync_helper::message_handler(msg, &mut sync_state) {
-          let _ = libs::error::warn_handler(senders.get("slack"), err);
+      let mut chain_head = 0;
+      let mut head_checked_at = tokio::time::Instant::now();
+      let mut health = HealthReporter::new();
+      let mut uptime = UptimeTracker::load();
+      let mut summary = BackfillSummary::new();
+      let archive = Archive::load(&sync_state.chain_id);
+      let reindex = backfill::reindex_activated();
+      loop {
+        if sync_state.is_workable() {
+          log::debug!("start to block sync process");
+          // the head is also refreshed by time, so the lag stays visible while the sync is stuck.
+          if !reindex && (chain_head == 0 || sync_state.sync_idx.is_multiple_of(100) || head_checked_at.elapsed() >= HEAD_REFRESH_INTERVAL) {
+            match Self::latest_height(&sync_state).await {
+              Ok(latest_height) => chain_head = latest_height,
+              Err(err) => log::warn!("failed to get latest height! error={}", err),
+            }
+            head_checked_at = tokio::time::Instant::now();
+          }
+          let bulk_lag = libs::opt::get_value::<u64>("block::bulk-lag").unwrap_or(1000);
+          let bulk = reindex || chain_head > sync_state.sync_idx + bulk_lag;
+          let synced = match archive.as_ref() {
+            Some(archive) if reindex => Self::reindex(&sync_state, &senders, uptime.as_mut(), archive),
+            _ => Self::sync(&sync_state, &senders, uptime.as_mut(), archive.as_ref(), bulk).await.map(Some),
+          };
+          match synced {
+            Err(err) => libs::sync_helper::error_handler(err, &mut sync_state, &senders),
+            // there is no other source to fail over to, so a reindex stops at the first height that is not archived.
+            Ok(None) => {
+              sync_state.error_msg = format!("height is not archived! height={}", sync_state.sync_idx);
+              sync_state.status(SyncStatus::Error);
+              log::error!("{}", sync_state.error_msg);
+            }
+            Ok(Some(num_txs)) => {
+              summary.synced(num_txs as u64);
+              sync_state.next_idx();
+              if let Err(err) = libs::sync_helper::checkpoint(&sync_state, &senders, bulk) {
+                log::error!("failed to checkpoint sync state! sync_id={}, error={}", sync_state.sync_id, err);
+              }
+            }
+          }
+          match sync_state.status {
+            SyncStatus::Completed => Self::completed(&sync_state, &senders, &summary),
+            SyncStatus::Error if backfill::activated() => summary.finish(&sync_state),
+            _ => {}
+          }
         }
-      }
-      if sync_state.is_workable() {
-        if let Err(err) = Self::sync(&sync_state, &senders).await {
-          libs::sync_helper::error_handler(err, &mut sync_state, &senders);
-        } else {
-          sync_state.next_idx();
+        health.report(&senders.get("alert"), &sync_state, chain_head);
+        // a reindex job sends no requests, so it does not wait between heights.
+        let poll_interval = if reindex { 0 } else { libs::opt::get_value::<u64>("block::poll-interval").unwrap_or(1000) };
+        tokio::select! {
+          biased;
+          _ = shutdown.stopped() => break,
+          received = receiver.recv() => match received {
+            Ok(msg) => {
+              if let Err(err) = libs::sync_helper::message_handler(msg, &mut sync_state).and_then(|_| libs::sync_helper::checkpoint(&sync_state, &senders, false)) {
+                libs::error::warn_handler(senders.get("slack"), err);
+              }
+            }
+            Err(RecvError::Lagged(skipped)) => log::warn!("block_sync channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = tokio::time::sleep(tokio::time::Duration::from_millis(poll_interval)), if sync_state.is_workable() => {}
         }
       }
-      if !app.is_quitting() {
-        let poll_interval = libs::opt::get_value::<u64>("block::poll-interval").unwrap_or(1000);
-        tokio::time::sleep(tokio::time::Duration::from_millis(poll_interval)).await;
-        Self::process(receiver, sync_state, senders, app);
-      }
+      Self::stop(&sync_state, &senders);
+      shutdown.done();
     });
   }
 
-  async fn sync(sync_state: &SyncState, senders: &MultiSender) -> Result<(), ExpectedError> {
+  fn completed(sync_state: &SyncState, senders: &MultiSender, summary: &BackfillSummary) {
+    let msg = format!("block sync completed. sync_id={}, from_idx={}, to_idx={}", sync_state.sync_id, sync_state.from_idx, sync_state.to_idx.unwrap_or_default());
+    log::info!("{}", msg);
+    let _ = senders.get("slack").send(SlackSyncMsg::new(SlackMsgLevel::Info.value(), msg, sync_state.sync_id.clone(), sync_state.sync_idx));
+    if backfill::activated() {
+      summary.finish(sync_state);
+    }
+  }
+
+  fn stop(sync_state: &SyncState, senders: &MultiSender) {
+    if let Err(err) = libs::sync_helper::checkpoint(sync_state, senders, false) {
+      log::error!("failed to save state on shutdown! sync_id={}, error={}", sync_state.sync_id, err);
+    }
+    let msg = format!("block sync stopped. sync_id={}, sync_idx={}", sync_state.sync_id, sync_state.sync_idx);
+    log::info!("{}", msg);
+    let _ = senders.get("slack").send(SlackSyncMsg::new(SlackMsgLevel::Info.value(), msg, sync_state.sync_id.clone(), sync_state.sync_idx));
+  }
+
+  async fn latest_height(sync_state: &SyncState) -> Result<u64, ExpectedError> {
+    let latest_req = libs::sync_helper::create_req_url(sync_state.active_node(), String::from("blocks/latest"));
+    let latest_res: BlockResponse = request::get_block_typed_async(latest_req.as_str(), "latest block").await?;
+    Ok(latest_res.block.header.height)
+  }
+
+  pub async fn sync(sync_state: &SyncState, senders: &MultiSender, uptime: Option<&mut UptimeTracker>, archive: Option<&Archive>, bulk: bool) -> Result<usize, ExpectedError> {
     let sync_block_api = format!("blocks/{}", sync_state.sync_idx);
     let block_req = libs::sync_helper::create_req_url(sync_state.active_node(), sync_block_api);
-    let mut block_res = request::get_block_async(block_req.as_str()).await?;
+    let block_body = request::get_block_body(block_req.as_str()).await?;
+    let block: BlockResponse = cosmos::decode("block", block_body.as_str())?;
+    // filtered blocks are archived with their txs too, so a reindex can use another filter.
+    let tx_pages = if archive.is_some() || filter(&cosmos::to_map(&block)?, sync_state.get_filter())? {
+      Self::fetch_txs(sync_state, block.num_txs()).await?
+    } else {
+      Vec::new()
+    };
+    if let Some(archive) = archive {
+      archive.write(sync_state.sync_idx, &block_body, &tx_pages)?;
+    }
+    Self::index(sync_state, senders, uptime, &block, &cosmos::decode_txs(&tx_pages)?, bulk)
+  }
+
+  /// none if the height is not archived.
+  fn reindex(sync_state: &SyncState, senders: &MultiSender, uptime: Option<&mut UptimeTracker>, archive: &Archive) -> Result<Option<usize>, ExpectedError> {
+    match archive.read(sync_state.sync_idx)? {
+      Some(archived) => {
+        let (block, txs) = archived.decode()?;
+        Self::index(sync_state, senders, uptime, &block, &txs, true).map(Some)
+      }
+      None => Ok(None),
+    }
+  }
+
+  /// returns the body of every page as it is received.
+  async fn fetch_txs(sync_state: &SyncState, num_txs: usize) -> Result<Vec<String>, ExpectedError> {
+    let mut tx_pages = Vec::new();
+    let total_page = (num_txs / 100) + if !num_txs.is_multiple_of(100) { 1 } else { 0 };
+    for i in 0..total_page {
+      let offset = i * 100;
+      let sync_tx_api = format!("cosmos/tx/v1beta1/txs?pagination.limit=100&pagination.offset={}&events=tx.height={}", offset, sync_state.sync_idx);
+      let txs_req = libs::sync_helper::create_req_url(sync_state.active_node(), sync_tx_api);
+      tx_pages.push(request::get_body(txs_req.as_str()).await?);
+    }
+    Ok(tx_pages)
+  }
+
+  /// maps a block and its txs to the schemas. a live sync and a reindex share it, so both produce the same rows.
+  fn index(sync_state: &SyncState, senders: &MultiSender, uptime: Option<&mut UptimeTracker>, block: &BlockResponse, txs: &[TxResponse], bulk: bool) -> Result<usize, ExpectedError> {
+    if let Some(uptime) = uptime {
+      Self::track_uptime(uptime, sync_state, senders, block, bulk)?;
+    }
+    let mut block_res = cosmos::to_map(block)?;
     if !filter(&block_res, sync_state.get_filter())? {
       return Err(ExpectedError::FilterError(format!("not matched filter condition! sync_type={}", sync_state.sync_type)));
     }
-    let num_txs = if let Some(txs) = find_value_by_path(&block_res, "block.data.txs").as_array() {
-      txs.len()
-    } else {
-      0
-    };
+    let num_txs = block.num_txs();
     block_res.insert("num_txs".to_string(), Value::from(num_txs));
 
     let pg_sender = senders.get("postgres");
-    let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_block"), Value::Object(block_res.clone()), -1))?;
-
-    if num_txs > 0 {
-      let total_page = (num_txs / 100) + if num_txs % 100 > 0 { 1 } else { 0 };
-      for i in 0..total_page {
-        let offset = i * 100;
-        let sync_tx_api = format!("cosmos/tx/v1beta1/txs?pagination.limit=100&pagination.offset={}&events=tx.height={}", offset, sync_state.sync_idx);
-        let txs_req = libs::sync_helper::create_req_url(sync_state.active_node(), sync_tx_api);
-        let txs_res = request::get_async(txs_req.as_str()).await?;
-        let tx_responses = get_array(&txs_res, "tx_responses")?;
-        let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_tx"), Value::Array(tx_responses.to_owned()), -1))?;
+    let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_block"), Value::Object(block_res), -1, sync_state.sync_idx, bulk))?;
+
+    for page in txs.chunks(100) {
+      let tx_responses = page.iter().map(|tx| cosmos::to_map(tx).map(Value::Object)).collect::<Result<Vec<Value>, ExpectedError>>()?;
+      let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_tx"), Value::Array(tx_responses), -1, sync_state.sync_idx, bulk))?;
+    }
+    Ok(num_txs)
+  }
+
+  // the last commit of a block holds the signatures for the previous height.
+  fn track_uptime(uptime: &mut UptimeTracker, sync_state: &SyncState, senders: &MultiSender, block: &BlockResponse, bulk: bool) -> Result<(), ExpectedError> {
+    let commit = &block.block.last_commit;
+    let notices = match uptime.record(commit) {
+      Some(notices) => notices,
+      None => return Ok(()),
+    };
+    let pg_sender = senders.get("postgres");
+    let commit_rows = commit_rows(commit, uptime.validators());
+    if !commit_rows.is_empty() {
+      let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_commit_sig"), Value::Array(commit_rows), -1, sync_state.sync_idx, bulk))?;
+    }
+    let uptime_rows = uptime.rows();
+    if !uptime_rows.is_empty() {
+      let _ = pg_sender.send(PostgresMsg::new(String::from("cosmos_validator_uptime"), Value::Array(uptime_rows), -1, sync_state.sync_idx, bulk))?;
+    }
+    // historical misses are stored, but not alerted.
+    if !bulk {
+      for notice in notices {
+        libs::error::sync_notice_handler(senders.get("slack"), sync_state, notice);
       }
     }
-    libs::sync_helper::save_state(&sync_state)?;
     Ok(())
   }
 }
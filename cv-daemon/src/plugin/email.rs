@@ -1,81 +1,143 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use appbase::prelude::*;
-use lettre::{Message, SmtpTransport, Transport};
-use lettre::transport::smtp::authentication::Credentials;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{Instant, interval_at};
 
 use crate::error::error::ExpectedError;
 use crate::libs;
+use crate::libs::email::{EmailConfig, Mailer, parse_mailboxes};
 use crate::libs::serde::get_str;
+use crate::libs::shutdown::ShutdownSignal;
+use crate::libs::notice::{Notice, Throttle};
 use crate::message;
 
 #[appbase_plugin]
 pub struct Email {
+  config: Option<EmailConfig>,
   monitor: Option<Receiver>,
+  alerts: Option<Receiver>,
+  shutdown: ShutdownSignal,
 }
 
 message!(EmailMsg; {to: String}, {subject: String}, {body: String});
 
 impl Plugin for Email {
   fn new() -> Self {
+    APP.options.arg(clap::Arg::new("email::activate").long("email-activate").takes_value(true));
     APP.options.arg(clap::Arg::new("email::smtp-username").long("smtp-username").takes_value(true));
     APP.options.arg(clap::Arg::new("email::smtp-password").long("smtp-password").takes_value(true));
     APP.options.arg(clap::Arg::new("email::smtp-relay").long("smtp-relay").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::smtp-port").long("smtp-port").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::tls").long("email-tls").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::accept-invalid-certs").long("email-accept-invalid-certs").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::pool-size").long("email-pool-size").takes_value(true));
     APP.options.arg(clap::Arg::new("email::from").long("email-from").takes_value(true));
     APP.options.arg(clap::Arg::new("email::reply-to").long("email-reply-to").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::info-to").long("email-info-to").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::warn-to").long("email-warn-to").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::error-to").long("email-error-to").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::subject-template").long("email-subject-template").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::body-template").long("email-body-template").takes_value(true));
+    APP.options.arg(clap::Arg::new("email::dedup-window").long("email-dedup-window").takes_value(true));
 
     Email {
+      config: None,
       monitor: None,
+      alerts: None,
+      shutdown: ShutdownSignal::new(),
     }
   }
 
   fn init(&mut self) {
+    if libs::opt::get_value::<bool>("email::activate").unwrap_or(false) {
+      self.config = Some(EmailConfig::load().expect("failed to load email config!"));
+    }
     self.monitor = Some(APP.channels.subscribe("email"));
+    // alerts are the same messages the slack plugin takes.
+    self.alerts = Some(APP.channels.subscribe("slack"));
   }
 
   fn startup(&mut self) {
     let monitor = self.monitor.take().unwrap();
-    let app = APP.quit_handle().unwrap();
-    Self::recv(monitor, app);
+    let alerts = self.alerts.take().unwrap();
+    Self::recv(self.config.take(), monitor, alerts, self.shutdown.clone());
   }
 
-  fn shutdown(&mut self) {}
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("email");
+  }
 }
 
 impl Email {
-  fn recv(mut monitor: Receiver, app: QuitHandle) {
+  fn recv(config: Option<EmailConfig>, mut monitor: Receiver, mut alerts: Receiver, shutdown: ShutdownSignal) {
     APP.spawn(async move {
-      if let Ok(msg) = monitor.try_recv() {
-        let parsed_msg = msg.as_object().unwrap();
-
-        let to = get_str(parsed_msg, "to").unwrap();
-        let subject = get_str(parsed_msg, "subject").unwrap();
-        let body = get_str(parsed_msg, "body").unwrap();
-
-        if let Err(result) = Self::send(to, subject, body) {
-          log::error!("{}", result);
+      // the pooled transport has to be built inside the runtime.
+      let mailer = match config.map(Mailer::new).transpose() {
+        Ok(mailer) => mailer,
+        Err(err) => {
+          log::error!("failed to build email transport! {}", err);
+          None
+        }
+      };
+      let dedup_window = Duration::from_millis(libs::opt::get_value::<u64>("email::dedup-window").unwrap_or(600000).max(1));
+      let mut throttle = Throttle::new(dedup_window, HashMap::new());
+      let mut digest_tick = interval_at(Instant::now() + dedup_window, dedup_window);
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => if let Some(mailer) = &mailer {
+              if let Err(err) = Self::send(mailer, msg).await {
+                log::error!("email delivery failed! {}", err);
+              }
+            },
+            Err(RecvError::Lagged(skipped)) => log::warn!("email channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          received = alerts.recv() => match received {
+            Ok(msg) => if let Some(mailer) = &mailer {
+              match Notice::from(msg.as_object().unwrap()) {
+                Ok(notice) => if mailer.has_recipients(&notice.level) && throttle.check(&notice, Instant::now().into_std()) {
+                  Self::alert(mailer, &notice).await;
+                },
+                Err(err) => log::error!("invalid email alert message! error={}", err),
+              }
+            },
+            Err(RecvError::Lagged(skipped)) => log::warn!("email alert channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = digest_tick.tick() => if let Some(mailer) = &mailer {
+            for notice in throttle.digest(Instant::now().into_std()) {
+              Self::alert(mailer, &notice).await;
+            }
+          },
+          _ = shutdown.stopped() => break,
         }
       }
-      if !app.is_quitting() {
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-        Self::recv(monitor, app);
+      if let Some(mailer) = &mailer {
+        for notice in throttle.digest(Instant::now().into_std()) {
+          Self::alert(mailer, &notice).await;
+        }
       }
+      shutdown.done();
     });
   }
 
-  pub fn send(to: &str, subject: &str, body: &str) -> Result<(), ExpectedError> {
-    let smtp_username = libs::opt::get_value_str("email::smtp-username")?;
-    let smtp_password = libs::opt::get_value_str("email::smtp-password")?;
-    let credentials = Credentials::new(smtp_username, smtp_password);
-    let smtp_relay = libs::opt::get_value_str("email::smtp-relay")?;
-    let from = libs::opt::get_value_str("email::from")?;
-    let reply_to = libs::opt::get_value_str("email::reply-to")?;
-
-    let email = Message::builder().from(from.as_str().parse().unwrap()).reply_to(reply_to.as_str().parse().unwrap()).to(to.parse().unwrap()).subject(subject).body(String::from(body)).unwrap();
-
-    let mailer = SmtpTransport::relay(smtp_relay.as_str()).unwrap().credentials(credentials).build();
+  async fn alert(mailer: &Mailer, notice: &Notice) {
+    if let Err(err) = mailer.send_alert(notice).await {
+      log::error!("email alert delivery failed! level={}, error={}", notice.level, err);
+    }
+  }
 
-    let _ = mailer.send(&email)?;
-    Ok(())
+  async fn send(mailer: &Mailer, msg: Value) -> Result<(), ExpectedError> {
+    let parsed_msg = msg.as_object().unwrap();
+    let to = parse_mailboxes(get_str(parsed_msg, "to")?)?;
+    let subject = get_str(parsed_msg, "subject")?;
+    let body = get_str(parsed_msg, "body")?;
+    mailer.send(to, String::from(subject), String::from(body)).await
   }
 }
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+
+use appbase::prelude::*;
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{Duration, Instant, interval_at};
+
+use crate::libs;
+use crate::error::error::ExpectedError;
+use crate::libs::opt::opt_to_result;
+use crate::libs::request;
+use crate::libs::serde::get_array;
+use crate::libs::shutdown::ShutdownSignal;
+use crate::libs::notice::{Notice, Throttle};
+use crate::types::notifier::NotifierTarget;
+
+#[appbase_plugin]
+pub struct Notifier {
+  targets: Option<Vec<NotifierTarget>>,
+  monitor: Option<Receiver>,
+  shutdown: ShutdownSignal,
+}
+
+impl Plugin for Notifier {
+  fn new() -> Self {
+    APP.options.arg(clap::Arg::new("notifier::activate").long("notifier-activate").takes_value(true));
+    APP.options.arg(clap::Arg::new("notifier::targets").long("notifier-targets").takes_value(true));
+    APP.options.arg(clap::Arg::new("notifier::dedup-window").long("notifier-dedup-window").takes_value(true));
+
+    Notifier {
+      targets: None,
+      monitor: None,
+      shutdown: ShutdownSignal::new(),
+    }
+  }
+
+  fn init(&mut self) {
+    let targets = if libs::opt::get_value::<bool>("notifier::activate").unwrap_or(false) {
+      Self::load_targets().expect("failed to load notifier targets!")
+    } else {
+      Vec::new()
+    };
+    self.targets = Some(targets);
+    // notifier takes the same messages as the slack plugin.
+    self.monitor = Some(APP.channels.subscribe("slack"));
+  }
+
+  fn startup(&mut self) {
+    let targets = self.targets.take().unwrap();
+    let monitor = self.monitor.take().unwrap();
+    Self::recv(targets, monitor, self.shutdown.clone());
+  }
+
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("notifier");
+  }
+}
+
+impl Notifier {
+  fn recv(targets: Vec<NotifierTarget>, mut monitor: Receiver, shutdown: ShutdownSignal) {
+    APP.spawn(async move {
+      let client = reqwest::Client::new();
+      let dedup_window = Duration::from_millis(libs::opt::get_value::<u64>("notifier::dedup-window").unwrap_or(600000).max(1));
+      // each target is throttled on its own, so a target that takes few levels is not starved by the others.
+      let mut targets: Vec<(NotifierTarget, Throttle)> = targets.into_iter().map(|target| (target, Throttle::new(dedup_window, HashMap::new()))).collect();
+      let mut digest_tick = interval_at(Instant::now() + dedup_window, dedup_window);
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => match Notice::from(msg.as_object().unwrap()) {
+              Ok(notice) => {
+                for (target, throttle) in targets.iter_mut().filter(|(target, _)| target.is_routed(&notice)) {
+                  if throttle.check(&notice, Instant::now().into_std()) {
+                    Self::notify(&client, target, &notice).await;
+                  }
+                }
+              }
+              Err(err) => log::error!("invalid notifier message! error={}", err),
+            },
+            Err(RecvError::Lagged(skipped)) => log::warn!("notifier channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = digest_tick.tick() => Self::digest(&client, &mut targets).await,
+          _ = shutdown.stopped() => break,
+        }
+      }
+      Self::digest(&client, &mut targets).await;
+      shutdown.done();
+    });
+  }
+
+  async fn digest(client: &reqwest::Client, targets: &mut [(NotifierTarget, Throttle)]) {
+    for (target, throttle) in targets.iter_mut() {
+      for notice in throttle.digest(Instant::now().into_std()) {
+        Self::notify(client, target, &notice).await;
+      }
+    }
+  }
+
+  async fn notify(client: &reqwest::Client, target: &NotifierTarget, notice: &Notice) {
+    let headers = target.headers.iter().map(|(header, value)| (header.as_str(), value.clone())).collect();
+    if let Err(err) = request::post_async(client, target.url.as_str(), target.render(notice).to_string(), headers).await {
+      log::error!("notifier delivery failed! target={}, error={}", target.name, err);
+    }
+  }
+
+  pub fn load_targets() -> Result<Vec<NotifierTarget>, ExpectedError> {
+    let targets_file = libs::opt::get_value_str("notifier::targets")?;
+    let json_str = fs::read_to_string(targets_file)?;
+    let json_targets: Value = serde_json::from_str(json_str.as_str())?;
+    let targets_map = opt_to_result(json_targets.as_object(), "notifier targets is not object!".to_string())?;
+    get_array(targets_map, "targets")?.iter().map(|target| {
+      NotifierTarget::from(opt_to_result(target.as_object(), "notifier target is not object!".to_string())?)
+    }).collect()
+  }
+}
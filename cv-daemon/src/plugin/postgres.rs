@@ -1,4 +1,4 @@
-use std::{fs, thread};
+use std::fs;
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
 use std::time::Duration;
@@ -8,29 +8,44 @@ use r2d2_postgres::{PostgresConnectionManager, r2d2};
 use r2d2_postgres::postgres::NoTls;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{libs, message};
 use crate::error::error::ExpectedError;
-use crate::libs::opt::opt_to_result;
-use crate::libs::postgres::{bulk_insert_value, create_table, insert_value};
+use crate::libs::alert::InsertStats;
+use crate::libs::migration::{create_sync_state_table, migrate};
+use crate::libs::postgres::PostgresWriter;
+use crate::libs::shutdown::ShutdownSignal;
+use crate::libs::sink::{FileSink, Sink, WebhookSink};
 use crate::libs::serde::get_str;
 use crate::plugin::slack::{SlackMsg, SlackMsgLevel};
+use crate::plugin::alert::Alert;
+use crate::plugin::email::Email;
+use crate::plugin::notifier::Notifier;
 use crate::plugin::slack::Slack;
+use crate::plugin::sync_manager::SyncManageMsg;
+use crate::plugin::watch::{Watch, WatchMsg};
+use crate::plugin::webhook::Webhook;
 use crate::types::channel::MultiSender;
 use crate::types::enumeration::Enumeration;
 use crate::types::postgres::PostgresSchema;
+use crate::types::sync::SyncMethod;
 
-#[appbase_plugin(Slack)]
+#[appbase_plugin(Slack, Notifier, Email, Alert, Webhook)]
 pub struct Postgres {
   monitor: Option<Receiver>,
   senders: Option<MultiSender>,
   pool: Option<Pool>,
   schema_map: Option<HashMap<String, PostgresSchema>>,
+  shutdown: ShutdownSignal,
 }
 
 pub type Pool = r2d2::Pool<PostgresConnectionManager<NoTls>>;
 
-message!(PostgresMsg; {schema: String}, {value: Value}, {version: i64});
+pub type Sinks = HashMap<String, Box<dyn Sink>>;
+
+message!(PostgresMsg; {schema: String}, {value: Value}, {version: i64}, {height: u64}, {bulk: bool});
+message!(SyncStateMsg; {sync_type: String}, {state: Value}, {bulk: bool});
 
 impl Plugin for Postgres {
   fn new() -> Self {
@@ -39,20 +54,39 @@ impl Plugin for Postgres {
     APP.options.arg(clap::Arg::new("postgres::dbname").long("postgres-dbname").takes_value(true));
     APP.options.arg(clap::Arg::new("postgres::user").long("postgres-user").takes_value(true));
     APP.options.arg(clap::Arg::new("postgres::password").long("postgres-password").takes_value(true));
+    APP.options.arg(clap::Arg::new("postgres::allow-destructive-migration").long("postgres-allow-destructive-migration").takes_value(true));
+    APP.options.arg(clap::Arg::new("postgres::bulk-size").long("postgres-bulk-size").takes_value(true));
+    APP.options.arg(clap::Arg::new("postgres::bulk-flush-interval").long("postgres-bulk-flush-interval").takes_value(true));
+    APP.options.arg(clap::Arg::new("postgres::sync-state").long("postgres-sync-state").takes_value(true));
+    APP.options.arg(clap::Arg::new("file::activate").long("file-activate").takes_value(true));
+    APP.options.arg(clap::Arg::new("file::dir").long("file-dir").takes_value(true));
+    APP.options.arg(clap::Arg::new("file::rotate-blocks").long("file-rotate-blocks").takes_value(true));
 
     Postgres {
       monitor: None,
       senders: None,
       pool: None,
       schema_map: None,
+      shutdown: ShutdownSignal::new(),
     }
   }
 
   fn init(&mut self) {
-    let schema_map = Self::load_schema().expect("failed to load schema!");
+    let schema_map = match Self::load_schema().and_then(Self::select_schemas) {
+      Ok(schema_map) => schema_map,
+      Err(err) => {
+        log::error!("failed to load schema!\n{}", err);
+        std::process::exit(1);
+      }
+    };
     let pool = Self::create_pool().expect("failed to create pool!");
-    create_table(pool.clone(), &schema_map).expect("failed to create tables!");
-    let senders = MultiSender::new(vec!("slack"));
+    let allow_destructive = libs::opt::get_value::<bool>("postgres::allow-destructive-migration").unwrap_or(false);
+    Self::check_sinks(&schema_map).expect("failed to route schema!");
+    migrate(pool.clone(), &schema_map, allow_destructive).expect("failed to migrate schema!");
+    if libs::sync_helper::state_table_activated() {
+      create_sync_state_table(&pool).expect("failed to create sync_state table!");
+    }
+    let senders = MultiSender::new(vec!("slack", "alert", "block_sync", "watch"));
     self.senders = Some(senders.to_owned());
     self.monitor = Some(APP.channels.subscribe("postgres"));
     self.pool = Some(pool);
@@ -60,72 +94,277 @@ impl Plugin for Postgres {
   }
 
   fn startup(&mut self) {
-    let pool = self.pool.as_ref().unwrap().clone();
+    let bulk_size = libs::opt::get_value::<usize>("postgres::bulk-size").unwrap_or(10000);
+    let flush_interval = libs::opt::get_value::<u64>("postgres::bulk-flush-interval").unwrap_or(5000);
+    let mut sinks: Sinks = HashMap::new();
+    sinks.insert(String::from("postgres"), Box::new(PostgresWriter::new(self.pool.as_ref().unwrap().clone(), bulk_size, Duration::from_millis(flush_interval), libs::sync_helper::state_table_activated())));
+    if Self::file_activated() {
+      let dir = libs::opt::get_value_str("file::dir").unwrap_or(String::from("data/"));
+      let rotate_blocks = libs::opt::get_value::<u64>("file::rotate-blocks").unwrap_or(100000);
+      sinks.insert(String::from("file"), Box::new(FileSink::new(dir, rotate_blocks)));
+    }
+    if Webhook::activated() {
+      sinks.insert(String::from("webhook"), Box::new(WebhookSink::new(APP.channels.get("webhook"))));
+    }
     let schema_map = self.schema_map.as_ref().unwrap().clone();
     let monitor = self.monitor.take().unwrap();
     let senders = self.senders.take().unwrap();
-    let app = APP.quit_handle().unwrap();
 
-    Self::process(pool, schema_map, senders, monitor, app);
+    Self::process(sinks, schema_map, senders, monitor, self.shutdown.clone());
   }
 
-  fn shutdown(&mut self) {}
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("postgres");
+  }
 }
 
 impl Postgres {
-  fn process(pool: Pool, schema_map: HashMap<String, PostgresSchema>, senders: MultiSender, mut monitor: Receiver, app: QuitHandle) {
+  pub fn process(mut sinks: Sinks, schema_map: HashMap<String, PostgresSchema>, senders: MultiSender, mut monitor: Receiver, shutdown: ShutdownSignal) {
     APP.spawn_blocking(move || {
-      if let Ok(mut msg) = monitor.try_recv() {
-        let parsed_msg = msg.borrow_mut().as_object_mut().unwrap();
-        let schema_name = get_str(parsed_msg, "schema").unwrap();
-        let selected_schema = schema_map.get(schema_name).unwrap();
-        let version = parsed_msg.get("version").unwrap().as_i64().unwrap();
-        let value = parsed_msg.get_mut("value").unwrap();
-
-        if value.is_object() {
-          let values = value.as_object_mut().unwrap();
-          if let Err(error) = insert_value(pool.clone(), selected_schema, values, version) {
-            log::error!("{}", error);
-            let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), error.to_string()));
+      let handle = tokio::runtime::Handle::current();
+      let idle_interval = Duration::from_millis(100);
+      let mut insert_stats = InsertStats::new();
+      let mut paused = false;
+      let mut watched = Vec::new();
+      loop {
+        let received = handle.block_on(async {
+          tokio::select! {
+            biased;
+            received = tokio::time::timeout(idle_interval, monitor.recv()) => Some(received),
+            _ = shutdown.stopped() => None,
+          }
+        });
+        match received {
+          Some(Ok(Ok(msg))) => {
+            if let Some(succeeded) = Self::write(&mut sinks, &schema_map, &senders, msg, &mut watched) {
+              insert_stats.record(succeeded);
+            }
           }
-        } else {
-          let values = parsed_msg.get_mut("value").unwrap().as_array_mut().unwrap();
-          if let Err(error) = bulk_insert_value(pool.clone(), selected_schema, values, version) {
-            log::error!("{}", error);
-            let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), error.to_string()));
+          Some(Ok(Err(RecvError::Lagged(skipped)))) => {
+            log::error!("postgres channel lagged! skipped={}", skipped);
+            let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Error.value(), format!("postgres channel lagged! {} messages are skipped.", skipped)));
           }
+          Some(Ok(Err(RecvError::Closed))) | None => break,
+          Some(Err(_)) => {
+            for (sink_name, sink) in sinks.iter_mut() {
+              if let Err(error) = sink.flush_idle() {
+                if sink_name == "postgres" {
+                  insert_stats.record(false);
+                }
+                log::error!("{}", error);
+                let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), format!("sink={}, {}", sink_name, error)));
+              }
+            }
+          }
+        }
+        insert_stats.report(&senders.get("alert"));
+        Self::rejected(&mut sinks, &senders);
+        Self::committed(&mut sinks, &senders, &mut watched);
+        let failed = sinks.get("postgres").is_some_and(|sink| sink.failed());
+        if failed != paused {
+          paused = failed;
+          Self::pause_sync(&senders, paused);
         }
       }
-      if !app.is_quitting() {
-        thread::sleep(Duration::from_millis(1));
-        Self::process(pool, schema_map, senders, monitor, app);
+      for (sink_name, sink) in sinks.iter_mut() {
+        if let Err(error) = sink.flush() {
+          log::error!("failed to flush sink on shutdown! sink={}, error={}", sink_name, error);
+          if sink_name == "postgres" {
+            libs::backfill::write_failed();
+          }
+          let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Error.value(), format!("sink={}, failed to flush on shutdown! {}", sink_name, error)));
+        }
       }
+      Self::rejected(&mut sinks, &senders);
+      Self::committed(&mut sinks, &senders, &mut watched);
+      log::info!("postgres plugin stopped.");
+      shutdown.done();
     });
   }
 
-  fn load_schema() -> Result<HashMap<String, PostgresSchema>, ExpectedError> {
-    let schema_dir = fs::read_dir("schema/").unwrap();
+  /// hands the sinks that wait for postgres, like webhooks, and the watch plugin the records that are committed now.
+  fn committed(sinks: &mut Sinks, senders: &MultiSender, watched: &mut Vec<Value>) {
+    if sinks.get("postgres").is_some_and(|sink| sink.pending()) {
+      return;
+    }
+    for (sink_name, sink) in sinks.iter_mut() {
+      if let Err(error) = sink.committed() {
+        log::error!("{}", error);
+        let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), format!("sink={}, {}", sink_name, error)));
+      }
+    }
+    for msg in watched.drain(..) {
+      let _ = senders.get("watch").send(msg);
+    }
+  }
+
+  /// rows that postgres rejected for their data are kept in the dead letter table, and the sync goes on without them.
+  fn rejected(sinks: &mut Sinks, senders: &MultiSender) {
+    let rejected = sinks.get_mut("postgres").map_or(0, |sink| sink.rejected());
+    if rejected == 0 {
+      return;
+    }
+    let msg = format!("postgres rejected {} rows! they are moved to cv_dead_letter.", rejected);
+    log::error!("{}", msg);
+    libs::backfill::rows_rejected(rejected as u64);
+    let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Error.value(), msg));
+  }
+
+  /// the rows of a write that failed on the connection are kept and retried on idle, and the sync waits for them instead of checkpointing past them.
+  fn pause_sync(senders: &MultiSender, paused: bool) {
+    let (method, level, msg) = if paused {
+      (SyncMethod::Pause, SlackMsgLevel::Error, "postgres write failed! the sync is paused until the kept rows are committed.")
+    } else {
+      (SyncMethod::Resume, SlackMsgLevel::Info, "postgres write recovered! the sync is resumed.")
+    };
+    log::warn!("{}", msg);
+    let _ = senders.get("block_sync").send(SyncManageMsg::new(method.value()));
+    let _ = senders.get("slack").send(SlackMsg::new(level.value(), String::from(msg)));
+  }
+
+  /// returns whether the postgres sink took the rows, or none if it was not written to.
+  /// the records are kept in `watched` until they are committed, unless postgres refused them.
+  pub fn write(sinks: &mut Sinks, schema_map: &HashMap<String, PostgresSchema>, senders: &MultiSender, mut msg: Value, watched: &mut Vec<Value>) -> Option<bool> {
+    let parsed_msg = msg.borrow_mut().as_object_mut().unwrap();
+    if let Some(state) = parsed_msg.get("state") {
+      let sync_type = get_str(parsed_msg, "sync_type").unwrap();
+      let bulk = parsed_msg.get("bulk").and_then(|bulk| bulk.as_bool()).unwrap_or(false);
+      for (sink_name, sink) in sinks.iter_mut() {
+        if let Err(error) = sink.checkpoint(sync_type, state, bulk) {
+          log::error!("{}", error);
+          let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), format!("sink={}, {}", sink_name, error)));
+        }
+      }
+      return None;
+    }
+    let source = String::from(get_str(parsed_msg, "schema").unwrap());
+    let version = parsed_msg.get("version").unwrap().as_i64().unwrap();
+    let height = parsed_msg.get("height").and_then(|height| height.as_u64()).unwrap_or_default();
+    let bulk = parsed_msg.get("bulk").and_then(|bulk| bulk.as_bool()).unwrap_or(false);
+    let source_rows = match parsed_msg.remove("value").unwrap() {
+      Value::Array(rows) => rows,
+      value => vec!(value),
+    };
+
+    let mut postgres_written = None;
+    let mut refused = false;
+    for selected_schema in schema_map.values().filter(|schema| schema.source == source) {
+      let mut rows = source_rows.clone();
+      for sink_name in selected_schema.sinks.iter() {
+        let sink = sinks.get_mut(sink_name).unwrap();
+        let result = sink.write(selected_schema, &mut rows, version, height, bulk);
+        if sink_name == "postgres" {
+          postgres_written = Some(postgres_written.unwrap_or(true) && result.is_ok());
+        }
+        if let Err(error) = result {
+          log::error!("{}", error);
+          let _ = senders.get("slack").send(SlackMsg::new(SlackMsgLevel::Warn.value(), format!("sink={}, {}", sink_name, error)));
+          if !sink.kept() {
+            if sink_name == "postgres" {
+              libs::backfill::write_failed();
+              refused = true;
+            }
+            break;
+          }
+        }
+      }
+    }
+    // bulk writes are historical catch-up, not new activity.
+    if !bulk && !refused && Watch::activated() {
+      watched.push(WatchMsg::new(source, height, Value::Array(source_rows)));
+    }
+    postgres_written
+  }
+
+  pub fn load_schema() -> Result<HashMap<String, PostgresSchema>, ExpectedError> {
     let mut schema_files: Vec<String> = Vec::new();
-    for file in schema_dir {
-      let file_name = file.unwrap().path().display().to_string();
+    for file in fs::read_dir("schema/")? {
+      let file_name = file?.path().display().to_string();
       if file_name.ends_with(".json") {
         log::debug!("add schema! schema_name={}", file_name);
         schema_files.push(file_name);
       }
     }
+    schema_files.sort();
+
     let mut schema_map = HashMap::new();
+    let mut schema_files_map: HashMap<String, String> = HashMap::new();
+    let mut problems = Vec::new();
     for schema_file in schema_files.iter() {
-      let json_str = fs::read_to_string(schema_file)?;
-      let json_schema: Value = serde_json::from_str(json_str.as_str())?;
-      let raw_schema_map = opt_to_result(json_schema.as_object(), "json_schema is non!".to_string()) ? ;
+      let json_schema: Value = match fs::read_to_string(schema_file).map_err(ExpectedError::from).and_then(|json_str| Ok(serde_json::from_str(json_str.as_str())?)) {
+        Ok(json_schema) => json_schema,
+        Err(err) => {
+          problems.push(format!("file={}: {}", schema_file, err));
+          continue;
+        }
+      };
+      let raw_schema_map = match json_schema.as_object() {
+        Some(raw_schema_map) => raw_schema_map,
+        None => {
+          problems.push(format!("file={}: schema file must be object of schemas!", schema_file));
+          continue;
+        }
+      };
       for (schema_name, values) in raw_schema_map {
-        schema_map.insert(schema_name.clone(), PostgresSchema::from(schema_name.clone(), values)?);
+        if let Some(other_file) = schema_files_map.get(schema_name) {
+          problems.push(format!("file={}, schema={}: schema is already defined in {}!", schema_file, schema_name, other_file));
+          continue;
+        }
+        schema_files_map.insert(schema_name.clone(), schema_file.clone());
+        match PostgresSchema::from(schema_name.clone(), values) {
+          Ok(schema) => {
+            let _ = schema_map.insert(schema_name.clone(), schema);
+          }
+          Err(err) => problems.extend(err.to_string().lines().map(|problem| format!("file={}, {}", schema_file, problem))),
+        }
       }
     }
+    if !problems.is_empty() {
+      return Err(ExpectedError::InvalidError(problems.join("\n")));
+    }
     Ok(schema_map)
   }
 
-  fn create_pool() -> Result<Pool, ExpectedError> {
+  /// a reindex job only migrates and writes the schemas listed in `--schemas`, so tables it was not asked for are left as they are.
+  fn select_schemas(mut schema_map: HashMap<String, PostgresSchema>) -> Result<HashMap<String, PostgresSchema>, ExpectedError> {
+    let selected = match libs::opt::get_value_str("schemas") {
+      Ok(selected) if libs::backfill::reindex_activated() => selected,
+      _ => return Ok(schema_map),
+    };
+    let selected: Vec<String> = selected.split(',').map(|schema_name| String::from(schema_name.trim())).filter(|schema_name| !schema_name.is_empty()).collect();
+    if let Some(unknown) = selected.iter().find(|schema_name| !schema_map.contains_key(*schema_name)) {
+      return Err(ExpectedError::InvalidError(format!("schema is not found! schema_name={}", unknown)));
+    }
+    schema_map.retain(|schema_name, _| selected.contains(schema_name));
+    Ok(schema_map)
+  }
+
+  pub fn pool(&self) -> Pool {
+    self.pool.clone().unwrap()
+  }
+
+  fn file_activated() -> bool {
+    libs::opt::get_value::<bool>("file::activate").unwrap_or(false)
+  }
+
+  pub fn check_sinks(schema_map: &HashMap<String, PostgresSchema>) -> Result<(), ExpectedError> {
+    for (schema_name, schema) in schema_map.iter() {
+      for sink_name in schema.sinks.iter() {
+        let activated = match sink_name.as_str() {
+          "postgres" => true,
+          "file" => Self::file_activated(),
+          "webhook" => Webhook::activated(),
+          _ => return Err(ExpectedError::InvalidError(format!("{} is not supported sink! schema_name={}", sink_name, schema_name))),
+        };
+        if !activated {
+          return Err(ExpectedError::InvalidError(format!("{} sink is not activated! schema_name={}", sink_name, schema_name)));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn create_pool() -> Result<Pool, ExpectedError> {
     let host = libs::opt::get_value_str("postgres::host")?;
     let port = libs::opt::get_value_str("postgres::port")?;
     let dbname = libs::opt::get_value_str("postgres::dbname")?;
@@ -134,8 +373,8 @@ impl Postgres {
 
     let config = format!("host={host} port={port} dbname={dbname} user={user} password={password}", host = host, port = port, dbname = dbname, user = user, password = password);
 
-    let manager = PostgresConnectionManager::new(config.parse().unwrap(), NoTls);
-    let pool: Pool = r2d2::Pool::builder().build(manager).expect("failed to create pool.");
+    let manager = PostgresConnectionManager::new(config.parse()?, NoTls);
+    let pool: Pool = r2d2::Pool::builder().build(manager)?;
     Ok(pool)
   }
 }
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use appbase::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{enumeration, libs};
 use crate::libs::opt;
-use crate::libs::serde::get_str;
+use crate::libs::shutdown::ShutdownSignal;
+use crate::libs::notice::{Notice, Throttle};
 use crate::message;
 use crate::types::enumeration::Enumeration;
 
@@ -14,11 +17,13 @@ use crate::types::enumeration::Enumeration;
 pub struct Slack {
   slack_hooks: Option<SlackHooks>,
   monitor: Option<Receiver>,
+  shutdown: ShutdownSignal,
 }
 
 pub type SlackHooks = HashMap<String, String>;
 
 message!(SlackMsg; {msg_level: String}, {msg: String});
+message!(SlackSyncMsg; {msg_level: String}, {msg: String}, {sync_id: String}, {height: u64});
 enumeration!(SlackMsgLevel; {Info: "info"}, {Warn: "warn"}, {Error: "error"});
 
 impl Plugin for Slack {
@@ -27,10 +32,16 @@ impl Plugin for Slack {
     APP.options.arg(clap::Arg::new("slack::info").long("slack-info").takes_value(true));
     APP.options.arg(clap::Arg::new("slack::warn").long("slack-warn").takes_value(true));
     APP.options.arg(clap::Arg::new("slack::error").long("slack-error").takes_value(true));
+    APP.options.arg(clap::Arg::new("slack::dedup-window").long("slack-dedup-window").takes_value(true));
+    APP.options.arg(clap::Arg::new("slack::digest-interval").long("slack-digest-interval").takes_value(true));
+    APP.options.arg(clap::Arg::new("slack::info-limit").long("slack-info-limit").takes_value(true));
+    APP.options.arg(clap::Arg::new("slack::warn-limit").long("slack-warn-limit").takes_value(true));
+    APP.options.arg(clap::Arg::new("slack::error-limit").long("slack-error-limit").takes_value(true));
 
     Slack {
       slack_hooks: None,
       monitor: None,
+      shutdown: ShutdownSignal::new(),
     }
   }
 
@@ -47,38 +58,119 @@ impl Plugin for Slack {
   fn startup(&mut self) {
     let slack_hooks = self.slack_hooks.take().unwrap();
     let monitor = self.monitor.take().unwrap();
-    let app = APP.quit_handle().unwrap();
-    Self::recv(slack_hooks, monitor, app);
+    Self::recv(slack_hooks, monitor, self.shutdown.clone());
   }
 
-  fn shutdown(&mut self) {}
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("slack");
+  }
 }
 
 impl Slack {
-  fn recv(slack_hooks: SlackHooks, mut monitor: Receiver, app: QuitHandle) {
+  pub fn recv(slack_hooks: SlackHooks, mut monitor: Receiver, shutdown: ShutdownSignal) {
     APP.spawn(async move {
-      if let Ok(msg) = monitor.try_recv() {
-        if libs::opt::get_value::<bool>("slack::activate").unwrap_or(false) {
-          let parsed_msg = msg.as_object().unwrap();
-          let msg_level = SlackMsgLevel::find(get_str(parsed_msg, "msg_level").unwrap()).unwrap();
-          let msg_level_value = msg_level.value();
-          let slack_hook = slack_hooks.get(&msg_level_value).unwrap();
-          let slack_msg = get_str(parsed_msg, "msg").unwrap();
-
-          let mut text = HashMap::new();
-          text.insert("text", slack_msg);
-          let client = reqwest::Client::new();
-          let result = client.post(slack_hook).json(&text).send().await;
-
-          if let Err(err) = result {
-            log::error!("slack error! error={:?}", err);
-          }
+      let mut throttle = Self::throttle();
+      // a zero period panics the interval, so it is at least 1ms.
+      let digest_interval = Duration::from_millis(libs::opt::get_value::<u64>("slack::digest-interval").unwrap_or(600000).max(1));
+      let mut digest_tick = tokio::time::interval_at(tokio::time::Instant::now() + digest_interval, digest_interval);
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => {
+              if libs::opt::get_value::<bool>("slack::activate").unwrap_or(false) {
+                match Notice::from(msg.as_object().unwrap()) {
+                  Ok(notice) if throttle.check(&notice, Instant::now()) => Self::send(&slack_hooks, &notice, digest_interval).await,
+                  Ok(notice) => log::debug!("slack message is suppressed! level={}, msg={}", notice.level, notice.msg),
+                  Err(err) => log::error!("invalid slack message! error={}", err),
+                }
+              }
+            }
+            Err(RecvError::Lagged(skipped)) => log::warn!("slack channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = digest_tick.tick() => {
+            for notice in throttle.digest(Instant::now()) {
+              Self::send(&slack_hooks, &notice, digest_interval).await;
+            }
+          },
+          _ = shutdown.stopped() => break,
         }
       }
-      if !app.is_quitting() {
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-        Self::recv(slack_hooks, monitor, app);
+      for notice in throttle.digest(Instant::now()) {
+        Self::send(&slack_hooks, &notice, digest_interval).await;
       }
+      shutdown.done();
     });
   }
+
+  fn throttle() -> Throttle {
+    let mut rate_limits = HashMap::new();
+    for (level, default_limit) in [("info", 30), ("warn", 10), ("error", 10)] {
+      let limit = libs::opt::get_value::<usize>(format!("slack::{}-limit", level).as_str()).unwrap_or(default_limit);
+      rate_limits.insert(String::from(level), limit);
+    }
+    let dedup_window = libs::opt::get_value::<u64>("slack::dedup-window").unwrap_or(600000);
+    Throttle::new(Duration::from_millis(dedup_window), rate_limits)
+  }
+
+  async fn send(slack_hooks: &SlackHooks, notice: &Notice, digest_interval: Duration) {
+    let slack_hook = match slack_hooks.get(&notice.level) {
+      Some(slack_hook) => slack_hook,
+      None => {
+        log::error!("unknown slack level! level={}", notice.level);
+        return;
+      }
+    };
+    let client = reqwest::Client::new();
+    let result = client.post(slack_hook).json(&Self::blocks(notice, digest_interval)).send().await;
+
+    if let Err(err) = result {
+      log::error!("slack error! error={:?}", err);
+    }
+  }
+
+  fn blocks(notice: &Notice, digest_interval: Duration) -> Value {
+    let msg = if notice.repeated > 0 {
+      format!("repeated {} times in {} min: {}", notice.repeated, (digest_interval.as_secs() / 60).max(1), notice.msg)
+    } else {
+      notice.msg.clone()
+    };
+    let mut context = Vec::new();
+    if let Some(sync_id) = &notice.sync_id {
+      context.push(json!({"type": "mrkdwn", "text": format!("sync_id: `{}`", sync_id)}));
+    }
+    if let Some(height) = notice.height {
+      context.push(json!({"type": "mrkdwn", "text": format!("height: `{}`", height)}));
+    }
+    let mut blocks = vec!(json!({"type": "section", "text": {"type": "mrkdwn", "text": format!("*{}* {}", notice.level.to_uppercase(), msg)}}));
+    if !context.is_empty() {
+      blocks.push(json!({"type": "context", "elements": context}));
+    }
+    json!({"text": format!("[{}] {}", notice.level, msg), "blocks": blocks})
+  }
+}
+
+#[cfg(test)]
+mod slack {
+  use std::time::Duration;
+
+  use serde_json::json;
+
+  use crate::libs::notice::Notice;
+  use crate::plugin::slack::Slack;
+
+  #[test]
+  fn blocks_test() {
+    let notice = Notice { level: String::from("error"), msg: String::from("endpoint failed!"), sync_id: None, height: Some(7), repeated: 0 };
+    assert_eq!(Slack::blocks(&notice, Duration::from_secs(600)), json!({
+      "text": "[error] endpoint failed!",
+      "blocks": [
+        { "type": "section", "text": { "type": "mrkdwn", "text": "*ERROR* endpoint failed!" } },
+        { "type": "context", "elements": [ { "type": "mrkdwn", "text": "height: `7`" } ] }
+      ]
+    }));
+    let digest = Notice { repeated: 340, ..notice };
+    assert_eq!(Slack::blocks(&digest, Duration::from_secs(600))["text"], "[error] repeated 340 times in 10 min: endpoint failed!");
+  }
 }
@@ -1,5 +1,3 @@
-use std::fs;
-
 use appbase::prelude::*;
 use jsonrpc_core::Params;
 use jsonrpc_core::serde_json::Map;
@@ -8,16 +6,19 @@ use serde_json::{json, Value};
 
 use crate::enumeration;
 use crate::error::error::ExpectedError;
+use crate::libs;
 use crate::libs::serde::get_str;
 use crate::message;
 use crate::plugin::jsonrpc::JsonRpc;
+use crate::plugin::postgres::{Pool, Postgres};
 use crate::types::channel::MultiSender;
 use crate::types::enumeration::Enumeration;
 use crate::types::sync::SyncMethod;
 
-#[appbase_plugin(JsonRpc)]
+#[appbase_plugin(JsonRpc, Postgres)]
 pub struct SyncManager {
   senders: Option<MultiSender>,
+  pool: Option<Pool>,
 }
 
 enumeration!(SyncType; {BlockSync: "block_sync"});
@@ -27,11 +28,15 @@ impl Plugin for SyncManager {
   fn new() -> Self {
     SyncManager {
       senders: None,
+      pool: None,
     }
   }
 
   fn init(&mut self) {
     self.senders = Some(MultiSender::new(vec!["block_sync"]));
+    if libs::sync_helper::state_table_activated() {
+      self.pool = Some(APP.run_with::<Postgres, _, _>(|postgres| postgres.pool()));
+    }
     self.add_methods();
   }
 
@@ -64,9 +69,10 @@ impl SyncManager {
       });
     });
 
+    let pool = self.pool.clone();
     APP.run_with::<JsonRpc, _, _>(|jsonrpc| {
       jsonrpc.add_method(String::from("get_sync"), move |params: Params| {
-        let response = match Self::get_sync(params) {
+        let response = match Self::get_sync(params, pool.as_ref()) {
           Ok(response) => response,
           Err(err) => json!({"error": err.to_string()}),
         };
@@ -84,12 +90,10 @@ impl SyncManager {
     Ok(Value::String(format!("requested! sync_type={}, method={}", sync_type, method.value())))
   }
 
-  fn get_sync(params: Params) -> Result<Value, ExpectedError> {
+  fn get_sync(params: Params, pool: Option<&Pool>) -> Result<Value, ExpectedError> {
     let params: Map<String, Value> = params.parse().unwrap();
     // let _ = task::verify(&params)?;
     let sync_type = get_str(&params, "sync_type")?;
-    let sync_state = fs::read_to_string(format!("state/{}.json", sync_type))?;
-    let state_json = serde_json::from_str(sync_state.as_str())?;
-    Ok(Value::Object(state_json))
+    libs::sync_helper::read_state(sync_type, pool)
   }
 }
@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use appbase::prelude::*;
+use jsonrpc_core::Params;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::error::error::ExpectedError;
+use crate::{libs, message};
+use crate::libs::opt::opt_to_result;
+use crate::libs::serde::{get_array, get_str, get_u64};
+use crate::libs::shutdown::ShutdownSignal;
+use crate::plugin::email::EmailMsg;
+use crate::plugin::jsonrpc::JsonRpc;
+use crate::plugin::slack::SlackMsg;
+use crate::plugin::webhook::WebhookMsg;
+use crate::types::channel::MultiSender;
+use crate::types::watch::{Watchlist, with_events};
+
+#[appbase_plugin(JsonRpc)]
+pub struct Watch {
+  watchlists: Watchlists,
+  senders: Option<MultiSender>,
+  monitor: Option<Receiver>,
+  shutdown: ShutdownSignal,
+}
+
+pub type Watchlists = Arc<Mutex<Vec<Watchlist>>>;
+
+message!(WatchMsg; {schema: String}, {height: u64}, {records: Value});
+
+impl Plugin for Watch {
+  fn new() -> Self {
+    APP.options.arg(clap::Arg::new("watch::activate").long("watch-activate").takes_value(true));
+    APP.options.arg(clap::Arg::new("watch::file").long("watch-file").takes_value(true));
+
+    Watch {
+      watchlists: Arc::new(Mutex::new(Vec::new())),
+      senders: None,
+      monitor: None,
+      shutdown: ShutdownSignal::new(),
+    }
+  }
+
+  fn init(&mut self) {
+    if Self::activated() {
+      *self.watchlists.lock().unwrap() = Self::load_watchlists().expect("failed to load watchlists!");
+      self.add_methods();
+    }
+    self.senders = Some(MultiSender::new(vec!("slack", "email", "webhook")));
+    // postgres sends the records here once they are committed, like it does for webhooks.
+    self.monitor = Some(APP.channels.subscribe("watch"));
+  }
+
+  fn startup(&mut self) {
+    let senders = self.senders.take().unwrap();
+    let monitor = self.monitor.take().unwrap();
+    Self::recv(self.watchlists.clone(), senders, monitor, self.shutdown.clone());
+  }
+
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("watch");
+  }
+}
+
+impl Watch {
+  pub fn activated() -> bool {
+    libs::opt::get_value::<bool>("watch::activate").unwrap_or(false)
+  }
+
+  fn add_methods(&self) {
+    let watchlists = self.watchlists.clone();
+    APP.run_with::<JsonRpc, _, _>(|jsonrpc| {
+      jsonrpc.add_method(String::from("add_watchlist"), move |params: Params| {
+        let response = match Self::add_watchlist(params, &watchlists) {
+          Ok(response) => response,
+          Err(err) => json!({"error": err.to_string()}),
+        };
+        Box::new(futures::future::ok(response))
+      });
+    });
+
+    let watchlists = self.watchlists.clone();
+    APP.run_with::<JsonRpc, _, _>(|jsonrpc| {
+      jsonrpc.add_method(String::from("remove_watchlist"), move |params: Params| {
+        let response = match Self::remove_watchlist(params, &watchlists) {
+          Ok(response) => response,
+          Err(err) => json!({"error": err.to_string()}),
+        };
+        Box::new(futures::future::ok(response))
+      });
+    });
+
+    let watchlists = self.watchlists.clone();
+    APP.run_with::<JsonRpc, _, _>(|jsonrpc| {
+      jsonrpc.add_method(String::from("get_watchlists"), move |_: Params| {
+        let response = json!(*watchlists.lock().unwrap());
+        Box::new(futures::future::ok(response))
+      });
+    });
+  }
+
+  fn add_watchlist(params: Params, watchlists: &Watchlists) -> Result<Value, ExpectedError> {
+    let params: Map<String, Value> = params.parse()?;
+    let watchlist = Watchlist::from(&params)?;
+    let mut watchlists = watchlists.lock().unwrap();
+    watchlists.retain(|watched| watched.name != watchlist.name);
+    let response = Value::String(format!("added! watchlist={}", watchlist.name));
+    watchlists.push(watchlist);
+    Self::save_watchlists(&watchlists)?;
+    Ok(response)
+  }
+
+  fn remove_watchlist(params: Params, watchlists: &Watchlists) -> Result<Value, ExpectedError> {
+    let params: Map<String, Value> = params.parse()?;
+    let name = get_str(&params, "name")?;
+    let mut watchlists = watchlists.lock().unwrap();
+    let before = watchlists.len();
+    watchlists.retain(|watched| watched.name != name);
+    if watchlists.len() == before {
+      return Err(ExpectedError::NoneError(format!("watchlist is not found! watchlist={}", name)));
+    }
+    Self::save_watchlists(&watchlists)?;
+    Ok(Value::String(format!("removed! watchlist={}", name)))
+  }
+
+  fn recv(watchlists: Watchlists, senders: MultiSender, mut monitor: Receiver, shutdown: ShutdownSignal) {
+    APP.spawn(async move {
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => Self::evaluate(&watchlists, &senders, msg),
+            Err(RecvError::Lagged(skipped)) => log::warn!("watch channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = shutdown.stopped() => break,
+        }
+      }
+      shutdown.done();
+    });
+  }
+
+  fn evaluate(watchlists: &Watchlists, senders: &MultiSender, msg: Value) {
+    let parsed_msg = msg.as_object().unwrap();
+    let schema = get_str(parsed_msg, "schema").unwrap();
+    let watchlists = watchlists.lock().unwrap().clone();
+    if !watchlists.iter().any(|watchlist| watchlist.schema == schema) {
+      return;
+    }
+    let height = get_u64(parsed_msg, "height").unwrap();
+    let records = get_array(parsed_msg, "records").unwrap();
+    for record in records.iter().filter_map(|record| record.as_object()) {
+      let record = with_events(record);
+      for watchlist in watchlists.iter() {
+        match watchlist.matches(schema, &record) {
+          Ok(true) => Self::notify(senders, watchlist, height, &record),
+          Ok(false) => {}
+          Err(err) => log::error!("failed to evaluate watchlist! watchlist={}, error={}", watchlist.name, err),
+        }
+      }
+    }
+  }
+
+  fn notify(senders: &MultiSender, watchlist: &Watchlist, height: u64, record: &Map<String, Value>) {
+    let txhash = record.get("txhash").and_then(|txhash| txhash.as_str()).map(|txhash| format!(", txhash={}", txhash)).unwrap_or_default();
+    let msg = format!("watchlist={} matched! schema={}, height={}{}", watchlist.name, watchlist.schema, height, txhash);
+    log::info!("{}", msg);
+    for channel in watchlist.channels.iter() {
+      let sent = match channel.as_str() {
+        "slack" => senders.get("slack").send(SlackMsg::new(watchlist.level.clone(), msg.clone())),
+        "email" => {
+          let subject = format!("[cv-daemon] watchlist {} matched", watchlist.name);
+          let body = format!("{}\n\n{}", msg, serde_json::to_string_pretty(record).unwrap_or_default());
+          senders.get("email").send(EmailMsg::new(watchlist.email_to.clone(), subject, body))
+        }
+        _ => senders.get("webhook").send(WebhookMsg::new(format!("watch::{}", watchlist.name), height, json!([record]))),
+      };
+      if let Err(err) = sent {
+        log::error!("failed to send watchlist match! watchlist={}, channel={}, error={}", watchlist.name, channel, err);
+      }
+    }
+  }
+
+  fn watchlist_file() -> String {
+    libs::opt::get_value_str("watch::file").unwrap_or(String::from("state/watchlists.json"))
+  }
+
+  pub fn load_watchlists() -> Result<Vec<Watchlist>, ExpectedError> {
+    let watchlist_file = Self::watchlist_file();
+    if !Path::new(&watchlist_file).exists() {
+      return Ok(Vec::new());
+    }
+    let json_str = fs::read_to_string(watchlist_file)?;
+    let json_watchlists: Value = serde_json::from_str(json_str.as_str())?;
+    let watchlists_map = opt_to_result(json_watchlists.as_object(), "watchlists is not object!".to_string())?;
+    get_array(watchlists_map, "watchlists")?.iter().map(|watchlist| {
+      Watchlist::from(opt_to_result(watchlist.as_object(), "watchlist is not object!".to_string())?)
+    }).collect()
+  }
+
+  fn save_watchlists(watchlists: &Vec<Watchlist>) -> Result<(), ExpectedError> {
+    let watchlist_file = Self::watchlist_file();
+    if let Some(parent) = Path::new(&watchlist_file).parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::write(watchlist_file, serde_json::to_string_pretty(&json!({"watchlists": watchlists}))?)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,209 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use appbase::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use crate::{libs, message};
+use crate::error::error::ExpectedError;
+use crate::libs::opt::opt_to_result;
+use crate::libs::request;
+use crate::libs::serde::{get_array, get_str, get_u64};
+use crate::libs::shutdown::ShutdownSignal;
+use crate::types::webhook::WebhookTarget;
+
+const MAX_RETRY_BACKOFF: u64 = 300000;
+
+#[appbase_plugin]
+pub struct Webhook {
+  targets: Option<Vec<WebhookTarget>>,
+  monitor: Option<Receiver>,
+  shutdown: ShutdownSignal,
+}
+
+message!(WebhookMsg; {schema: String}, {height: u64}, {records: Value});
+
+impl Plugin for Webhook {
+  fn new() -> Self {
+    APP.options.arg(clap::Arg::new("webhook::activate").long("webhook-activate").takes_value(true));
+    APP.options.arg(clap::Arg::new("webhook::targets").long("webhook-targets").takes_value(true));
+    APP.options.arg(clap::Arg::new("webhook::max-retries").long("webhook-max-retries").takes_value(true));
+    APP.options.arg(clap::Arg::new("webhook::retry-backoff").long("webhook-retry-backoff").takes_value(true));
+    APP.options.arg(clap::Arg::new("webhook::dead-letter").long("webhook-dead-letter").takes_value(true));
+    APP.options.arg(clap::Arg::new("webhook::queue-size").long("webhook-queue-size").takes_value(true));
+
+    Webhook {
+      targets: None,
+      monitor: None,
+      shutdown: ShutdownSignal::new(),
+    }
+  }
+
+  fn init(&mut self) {
+    let targets = if Self::activated() {
+      Self::load_targets().expect("failed to load webhook targets!")
+    } else {
+      Vec::new()
+    };
+    self.targets = Some(targets);
+    self.monitor = Some(APP.channels.subscribe("webhook"));
+  }
+
+  fn startup(&mut self) {
+    let targets = self.targets.take().unwrap();
+    let monitor = self.monitor.take().unwrap();
+    Self::recv(targets, monitor, self.shutdown.clone());
+  }
+
+  fn shutdown(&mut self) {
+    self.shutdown.stop_and_wait("webhook");
+  }
+}
+
+impl Webhook {
+  pub fn activated() -> bool {
+    libs::opt::get_value::<bool>("webhook::activate").unwrap_or(false)
+  }
+
+  fn recv(targets: Vec<WebhookTarget>, mut monitor: Receiver, shutdown: ShutdownSignal) {
+    APP.spawn(async move {
+      let client = reqwest::Client::new();
+      let queue_size = libs::opt::get_value::<usize>("webhook::queue-size").unwrap_or(1000).max(1);
+      // every worker holds a sender, so `stopped` resolves once all of them have drained their queue.
+      let (worker_done, mut stopped) = mpsc::channel::<()>(1);
+      let queues: Vec<(WebhookTarget, mpsc::Sender<String>)> = targets.into_iter().map(|target| {
+        let (queue, payloads) = mpsc::channel(queue_size);
+        Self::work(client.clone(), target.clone(), payloads, shutdown.clone(), worker_done.clone());
+        (target, queue)
+      }).collect();
+      drop(worker_done);
+      loop {
+        tokio::select! {
+          biased;
+          received = monitor.recv() => match received {
+            Ok(msg) => {
+              let parsed_msg = msg.as_object().unwrap();
+              let schema_name = get_str(parsed_msg, "schema").unwrap();
+              for (target, queue) in queues.iter().filter(|(target, _)| target.is_routed(schema_name)) {
+                for payload in Self::payloads(target, parsed_msg) {
+                  if queue.send(payload).await.is_err() {
+                    log::error!("webhook queue is closed! target={}", target.name);
+                  }
+                }
+              }
+            }
+            Err(RecvError::Lagged(skipped)) => log::error!("webhook channel lagged! skipped={}", skipped),
+            Err(RecvError::Closed) => break,
+          },
+          _ = shutdown.stopped() => break,
+        }
+      }
+      drop(queues);
+      let _ = stopped.recv().await;
+      shutdown.done();
+    });
+  }
+
+  /// delivers the payloads of a target one at a time, in the order they are queued, until the queue is closed.
+  fn work(client: reqwest::Client, target: WebhookTarget, mut payloads: mpsc::Receiver<String>, shutdown: ShutdownSignal, done: mpsc::Sender<()>) {
+    APP.spawn(async move {
+      while let Some(payload) = payloads.recv().await {
+        Self::deliver(&client, &target, payload, &shutdown).await;
+      }
+      drop(done);
+    });
+  }
+
+  fn payloads(target: &WebhookTarget, parsed_msg: &Map<String, Value>) -> Vec<String> {
+    let schema_name = get_str(parsed_msg, "schema").unwrap();
+    let height = get_u64(parsed_msg, "height").unwrap();
+    let records = get_array(parsed_msg, "records").unwrap();
+    if target.batch {
+      vec!(json!({"schema": schema_name, "height": height, "records": records}).to_string())
+    } else {
+      records.iter().map(|record| json!({"schema": schema_name, "height": height, "record": record}).to_string()).collect()
+    }
+  }
+
+  /// once the plugin is stopping, a failed delivery is not retried but written to the dead letter file.
+  async fn deliver(client: &reqwest::Client, target: &WebhookTarget, payload: String, shutdown: &ShutdownSignal) {
+    let max_retries = libs::opt::get_value::<u32>("webhook::max-retries").unwrap_or(5);
+    let retry_backoff = libs::opt::get_value::<u64>("webhook::retry-backoff").unwrap_or(1000);
+    let mut attempt = 0;
+    loop {
+      let error = match Self::post(client, target, &payload).await {
+        Ok(_) => return,
+        Err(error) => error,
+      };
+      if attempt >= max_retries || shutdown.is_stopped() {
+        log::error!("webhook delivery failed! target={}, error={}", target.name, error);
+        if let Err(err) = Self::dead_letter(target, &payload, &error) {
+          log::error!("failed to write webhook dead letter! target={}, error={}", target.name, err);
+        }
+        return;
+      }
+      let backoff = Self::backoff(retry_backoff, attempt);
+      log::warn!("webhook delivery failed! retry after {}ms. target={}, attempt={}, error={}", backoff, target.name, attempt + 1, error);
+      tokio::select! {
+        biased;
+        _ = shutdown.stopped() => {},
+        _ = tokio::time::sleep(tokio::time::Duration::from_millis(backoff)) => {},
+      }
+      attempt += 1;
+    }
+  }
+
+  /// doubles the wait on each attempt, up to five minutes.
+  fn backoff(retry_backoff: u64, attempt: u32) -> u64 {
+    2u64.checked_pow(attempt).map_or(u64::MAX, |multiplier| retry_backoff.saturating_mul(multiplier)).min(MAX_RETRY_BACKOFF)
+  }
+
+  async fn post(client: &reqwest::Client, target: &WebhookTarget, payload: &str) -> Result<(), ExpectedError> {
+    let mut headers = vec!(("X-Cv-Target", target.name.clone()));
+    if let Some(secret) = &target.secret {
+      headers.push(("X-Cv-Signature", format!("sha256={}", request::hmac_sha256(secret, payload)?)));
+    }
+    request::post_async(client, target.url.as_str(), payload.to_string(), headers).await
+  }
+
+  fn dead_letter(target: &WebhookTarget, payload: &str, error: &ExpectedError) -> Result<(), ExpectedError> {
+    let dead_letter = libs::opt::get_value_str("webhook::dead-letter").unwrap_or(String::from("state/webhook_dead_letter.jsonl"));
+    if let Some(parent) = Path::new(&dead_letter).parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let payload: Value = serde_json::from_str(payload)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&dead_letter)?;
+    // one write per line, so the lines of the workers are not interleaved.
+    file.write_all(format!("{}\n", json!({"target": target.name, "url": target.url, "error": error.to_string(), "payload": payload})).as_bytes())?;
+    Ok(())
+  }
+
+  pub fn load_targets() -> Result<Vec<WebhookTarget>, ExpectedError> {
+    let targets_file = libs::opt::get_value_str("webhook::targets")?;
+    let json_str = fs::read_to_string(targets_file)?;
+    let json_targets: Value = serde_json::from_str(json_str.as_str())?;
+    let targets_map = opt_to_result(json_targets.as_object(), "webhook targets is not object!".to_string())?;
+    get_array(targets_map, "targets")?.iter().map(|target| {
+      WebhookTarget::from(opt_to_result(target.as_object(), "webhook target is not object!".to_string())?)
+    }).collect()
+  }
+}
+
+#[cfg(test)]
+mod webhook {
+  use crate::plugin::webhook::Webhook;
+
+  #[test]
+  fn backoff_test() {
+    assert_eq!(Webhook::backoff(1000, 0), 1000);
+    assert_eq!(Webhook::backoff(1000, 3), 8000);
+    assert_eq!(Webhook::backoff(1000, 20), 300000);
+    assert_eq!(Webhook::backoff(1000, 64), 300000);
+    assert_eq!(Webhook::backoff(u64::MAX, 1), 300000);
+  }
+}
@@ -1,5 +1,9 @@
 pub mod sync;
 pub mod channel;
+pub mod cosmos;
 pub mod enumeration;
 pub mod message;
+pub mod notifier;
 pub mod postgres;
+pub mod webhook;
+pub mod watch;
@@ -18,6 +18,13 @@ impl MultiSender {
     }
   }
 
+  /// sends to the given senders instead of the app channels, e.g. to read what a task sends.
+  pub fn from(sender_map: HashMap<String, Sender>) -> Self {
+    MultiSender {
+      sender_map
+    }
+  }
+
   pub fn get(&self, name: &str) -> Sender {
     match self.sender_map.get(name) {
       None => APP.channels.get(name),
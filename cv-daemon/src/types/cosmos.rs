@@ -0,0 +1,249 @@
+use chrono::DateTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::DeserializeOwned;
+use serde::de::Error;
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockResponse {
+  pub block_id: BlockId,
+  pub block: Block,
+  #[serde(flatten)]
+  pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockId {
+  pub hash: String,
+  #[serde(default)]
+  pub parts: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Block {
+  pub header: Header,
+  pub data: BlockData,
+  #[serde(default)]
+  pub evidence: Value,
+  pub last_commit: Commit,
+  #[serde(flatten)]
+  pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+  pub version: HeaderVersion,
+  pub chain_id: String,
+  #[serde(with = "string_u64")]
+  pub height: u64,
+  #[serde(deserialize_with = "rfc3339")]
+  pub time: String,
+  pub last_block_id: BlockId,
+  pub last_commit_hash: String,
+  pub data_hash: String,
+  pub validators_hash: String,
+  pub next_validators_hash: String,
+  pub consensus_hash: String,
+  pub app_hash: String,
+  pub last_results_hash: String,
+  pub evidence_hash: String,
+  pub proposer_address: String,
+  #[serde(flatten)]
+  pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderVersion {
+  pub block: String,
+  #[serde(default)]
+  pub app: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockData {
+  #[serde(default, deserialize_with = "null_as_empty")]
+  pub txs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commit {
+  #[serde(with = "string_u64")]
+  pub height: u64,
+  pub round: Value,
+  pub block_id: BlockId,
+  #[serde(default, deserialize_with = "null_as_empty")]
+  pub signatures: Vec<CommitSig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitSig {
+  pub block_id_flag: Value,
+  #[serde(default)]
+  pub validator_address: Option<String>,
+  #[serde(default)]
+  pub timestamp: Option<String>,
+  #[serde(default)]
+  pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxsResponse {
+  #[serde(default, deserialize_with = "null_as_empty")]
+  pub tx_responses: Vec<TxResponse>,
+  #[serde(default)]
+  pub pagination: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxResponse {
+  #[serde(with = "string_u64")]
+  pub height: u64,
+  pub txhash: String,
+  #[serde(default)]
+  pub code: u32,
+  #[serde(with = "string_u64")]
+  pub gas_wanted: u64,
+  #[serde(with = "string_u64")]
+  pub gas_used: u64,
+  #[serde(default)]
+  pub tx: Value,
+  #[serde(deserialize_with = "rfc3339")]
+  pub timestamp: String,
+  #[serde(flatten)]
+  pub extra: Map<String, Value>,
+}
+
+impl BlockResponse {
+  pub fn num_txs(&self) -> usize {
+    self.block.data.txs.len()
+  }
+}
+
+pub fn decode<T: DeserializeOwned>(type_name: &str, body: &str) -> Result<T, ExpectedError> {
+  let deserializer = &mut serde_json::Deserializer::from_str(body);
+  serde_path_to_error::deserialize(deserializer).map_err(|err| {
+    ExpectedError::ParsingError(format!("failed to decode {}! field={}, error={}", type_name, err.path(), err.inner()))
+  })
+}
+
+/// decodes the pages of a txs response into their tx responses.
+pub fn decode_txs(pages: &[String]) -> Result<Vec<TxResponse>, ExpectedError> {
+  let mut txs = Vec::new();
+  for page in pages.iter() {
+    let txs_res: TxsResponse = decode("txs", page.as_str())?;
+    txs.extend(txs_res.tx_responses);
+  }
+  Ok(txs)
+}
+
+pub fn to_map<T: Serialize>(value: &T) -> Result<Map<String, Value>, ExpectedError> {
+  match serde_json::to_value(value)? {
+    Value::Object(map) => Ok(map),
+    value => Err(ExpectedError::TypeError(format!("{} is not object!", value))),
+  }
+}
+
+fn rfc3339<'de, D>(deserializer: D) -> Result<String, D::Error> where D: Deserializer<'de> {
+  let time = String::deserialize(deserializer)?;
+  DateTime::parse_from_rfc3339(time.as_str()).map_err(|err| D::Error::custom(format!("{} is not rfc3339 format! {}", time, err)))?;
+  Ok(time)
+}
+
+fn null_as_empty<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error> where D: Deserializer<'de>, T: Deserialize<'de> {
+  Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+mod string_u64 {
+  use super::*;
+
+  pub fn serialize<S>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    serializer.serialize_str(value.to_string().as_str())
+  }
+
+  pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error> where D: Deserializer<'de> {
+    let value = String::deserialize(deserializer)?;
+    value.parse::<u64>().map_err(|err| D::Error::custom(format!("{} is not integer string! {}", value, err)))
+  }
+}
+
+#[cfg(test)]
+mod cosmos {
+  use serde_json::json;
+
+  use crate::types::cosmos::{BlockResponse, decode, to_map, TxsResponse};
+
+  fn block_json() -> serde_json::Value {
+    json!({
+      "block_id": { "hash": "A1", "parts": { "total": 1, "hash": "B1" } },
+      "block": {
+        "header": {
+          "version": { "block": "11" },
+          "chain_id": "cosmoshub-4",
+          "height": "7135432",
+          "time": "2021-08-18T21:41:12.531940473Z",
+          "last_block_id": { "hash": "A0", "parts": { "total": 1, "hash": "B0" } },
+          "last_commit_hash": "C1",
+          "data_hash": "D1",
+          "validators_hash": "E1",
+          "next_validators_hash": "E1",
+          "consensus_hash": "F1",
+          "app_hash": "G1",
+          "last_results_hash": "H1",
+          "evidence_hash": "",
+          "proposer_address": "I1"
+        },
+        "data": { "txs": null },
+        "evidence": { "evidence": [] },
+        "last_commit": {
+          "height": "7135431",
+          "round": 0,
+          "block_id": { "hash": "A0", "parts": { "total": 1, "hash": "B0" } },
+          "signatures": [ { "block_id_flag": 2, "validator_address": "J1", "timestamp": "2021-08-18T21:41:12.531940473Z", "signature": "K1" } ]
+        }
+      }
+    })
+  }
+
+  #[test]
+  fn decode_block_test() {
+    let block: BlockResponse = decode("block", block_json().to_string().as_str()).unwrap();
+    assert_eq!(block.block.header.height, 7135432);
+    assert_eq!(block.num_txs(), 0);
+    assert_eq!(block.block.last_commit.signatures.len(), 1);
+
+    let map = to_map(&block).unwrap();
+    assert_eq!(map["block"]["header"]["height"], json!("7135432"));
+    assert_eq!(map["block"]["header"]["proposer_address"], json!("I1"));
+  }
+
+  #[test]
+  fn decode_block_fail_test() {
+    let mut block = block_json();
+    block["block"]["header"]["height"] = json!("71354a2");
+    let err = decode::<BlockResponse>("block", block.to_string().as_str()).err().unwrap();
+    assert!(err.to_string().starts_with("failed to decode block! field=block.header.height, error=71354a2 is not integer string!"));
+
+    let mut block = block_json();
+    block["block"]["header"].as_object_mut().unwrap().remove("chain_id");
+    let err = decode::<BlockResponse>("block", block.to_string().as_str()).err().unwrap();
+    assert!(err.to_string().contains("field=block.header, error=missing field `chain_id`"));
+  }
+
+  #[test]
+  fn decode_txs_test() {
+    let txs = json!({
+      "txs": [],
+      "tx_responses": [ {
+        "height": "7135432", "txhash": "AB", "code": 0, "gas_wanted": "200000", "gas_used": "80000",
+        "tx": { "body": { "memo": "" } }, "timestamp": "2021-08-18T21:41:12Z", "raw_log": "[]"
+      } ],
+      "pagination": { "next_key": null, "total": "1" }
+    });
+    let txs: TxsResponse = decode("txs", txs.to_string().as_str()).unwrap();
+    let tx = to_map(&txs.tx_responses[0]).unwrap();
+    assert_eq!(tx["raw_log"], json!("[]"));
+    assert_eq!(tx["gas_used"], json!("80000"));
+  }
+}
@@ -37,7 +37,6 @@ macro_rules! enumeration {
 mod enumeration_test {
   use serde::{Deserialize, Serialize};
 
-  use crate::enumeration;
   use crate::types::enumeration::Enumeration;
 
   enumeration!(TestType; {A: "a"}, {B: "b"});
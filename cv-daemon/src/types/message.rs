@@ -25,7 +25,7 @@ macro_rules! message {
         }
 
         impl $message {
-            #[allow(dead_code)]
+            #[allow(dead_code, clippy::new_ret_no_self)]
             pub fn new($($message_names: $message_types,)*) -> Value {
                 json!(Self {
                     $($message_names,)*
@@ -0,0 +1,97 @@
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs::opt::opt_to_result;
+use crate::libs::serde::get_string;
+use crate::libs::notice::Notice;
+
+#[derive(Clone, Debug)]
+pub struct NotifierTarget {
+  pub name: String,
+  pub url: String,
+  pub levels: Vec<String>,
+  pub sync_ids: Vec<String>,
+  pub headers: Vec<(String, String)>,
+  pub template: Value,
+}
+
+impl NotifierTarget {
+  pub fn from(params: &Map<String, Value>) -> Result<Self, ExpectedError> {
+    let name = get_string(params, "name")?;
+    let headers = match params.get("headers") {
+      None => Vec::new(),
+      Some(Value::Object(headers)) => headers.iter().map(|(header, value)| {
+        let value = opt_to_result(value.as_str(), format!("notifier header must be string! target={}, header={}", name, header))?;
+        Ok((header.clone(), String::from(value)))
+      }).collect::<Result<Vec<(String, String)>, ExpectedError>>()?,
+      Some(_) => return Err(ExpectedError::InvalidError(format!("notifier headers must be object! target={}", name))),
+    };
+    Ok(NotifierTarget {
+      url: get_string(params, "url")?,
+      levels: Self::string_vec(&name, params, "levels")?,
+      sync_ids: Self::string_vec(&name, params, "sync_ids")?,
+      headers,
+      template: opt_to_result(params.get("template").cloned(), format!("notifier target must include template! target={}", name))?,
+      name,
+    })
+  }
+
+  fn string_vec(name: &String, params: &Map<String, Value>, key: &str) -> Result<Vec<String>, ExpectedError> {
+    match params.get(key) {
+      None => Ok(Vec::new()),
+      Some(Value::Array(items)) => items.iter().map(|item| {
+        opt_to_result(item.as_str().map(String::from), format!("notifier {} must be array of string! target={}", key, name))
+      }).collect(),
+      Some(_) => Err(ExpectedError::InvalidError(format!("notifier {} must be array of string! target={}", key, name))),
+    }
+  }
+
+  pub fn is_routed(&self, notice: &Notice) -> bool {
+    let level_routed = self.levels.is_empty() || self.levels.contains(&notice.level);
+    let sync_id_routed = self.sync_ids.is_empty() || notice.sync_id.as_ref().is_some_and(|sync_id| self.sync_ids.contains(sync_id));
+    level_routed && sync_id_routed
+  }
+
+  /// renders every string of the template with the notice.
+  pub fn render(&self, notice: &Notice) -> Value {
+    Self::render_value(&self.template, notice)
+  }
+
+  fn render_value(template: &Value, notice: &Notice) -> Value {
+    match template {
+      Value::String(text) => Value::String(notice.render(text.as_str())),
+      Value::Array(items) => Value::Array(items.iter().map(|item| Self::render_value(item, notice)).collect()),
+      Value::Object(map) => Value::Object(map.iter().map(|(key, value)| (key.clone(), Self::render_value(value, notice))).collect()),
+      value => value.clone(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod notifier {
+  use serde_json::json;
+
+  use crate::libs::notice::Notice;
+  use crate::types::notifier::NotifierTarget;
+
+  #[test]
+  fn render_test() {
+    let params = json!({
+      "name": "pagerduty",
+      "url": "https://events.pagerduty.com/v2/enqueue",
+      "levels": [ "error" ],
+      "sync_ids": [ "cosmos::cosmoshub-4::block_sync" ],
+      "template": { "event_action": "trigger", "payload": { "summary": "{msg}", "source": "{sync_id}@{height}", "severity": "{level}" } }
+    });
+    let target = NotifierTarget::from(params.as_object().unwrap()).unwrap();
+    let notice = Notice { level: String::from("error"), msg: String::from("endpoint \"a\" failed! {level}"), sync_id: Some(String::from("cosmos::cosmoshub-4::block_sync")), height: Some(10), repeated: 0 };
+
+    assert!(target.is_routed(&notice));
+    assert!(!target.is_routed(&Notice { level: String::from("warn"), ..notice.clone() }));
+    assert!(!target.is_routed(&Notice { sync_id: None, ..notice.clone() }));
+    assert_eq!(target.render(&notice), json!({
+      "event_action": "trigger",
+      "payload": { "summary": "endpoint \"a\" failed! {level}", "source": "cosmos::cosmoshub-4::block_sync@10", "severity": "error" }
+    }));
+  }
+}
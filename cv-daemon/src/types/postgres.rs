@@ -1,34 +1,70 @@
 use std::fmt::Debug;
 
 use jsonrpc_core::Value;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use sha2::{Digest, Sha256};
 
+use crate::enumeration;
 use crate::error::error::ExpectedError;
 use crate::libs::postgres::convert_type;
-use crate::libs::serde::{get_array, get_object};
+use crate::libs::opt::opt_to_result;
+use crate::libs::serde::{get_array, get_object, get_str};
+use crate::types::enumeration::Enumeration;
 
 #[derive(Clone, Debug)]
 pub struct PostgresSchema {
   pub schema_name: String,
+  pub version: String,
   pub attributes: Vec<Attribute>,
+  pub indexes: Vec<Vec<String>>,
+  pub uniques: Vec<Vec<String>>,
   pub create_table: String,
   pub create_index: Vec<String>,
   pub insert_query: String,
-  pub values_format: String,
+  pub on_conflict: OnConflict,
+  pub versioned: bool,
+  pub sinks: Vec<String>,
+  pub source: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct OnConflict {
+  pub action: ConflictAction,
+  pub target: Vec<String>,
+  pub columns: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Attribute {
   pub name: String,
   pub description: String,
-  _type: String,
+  pub transform: Option<Transform>,
+  pub template: Option<String>,
+  pub default: Option<Value>,
+  pub _type: String,
+  pub items: Option<String>,
   max_length: Option<u32>,
-  nullable: bool,
+  pub nullable: bool,
 }
 
+const MAX_VARCHAR_LENGTH: u64 = 10485760;
+const MAX_NUMERIC_PRECISION: u64 = 1000;
+
+const RESERVED_TABLES: [&str; 3] = ["cv_schema_migration", "cv_dead_letter", "sync_state"];
+
+/// a version of a versioned row is valid from the height it was indexed at until the height of the next version.
+const HISTORY_COLUMNS: [&str; 3] = ["version", "valid_from", "valid_to"];
+
+enumeration!(ConflictAction; {Ignore: "ignore"}, {Update: "update"}, {Fail: "fail"});
+enumeration!(Transform; {Integer: "integer"}, {Timestamp: "timestamp"}, {Base64: "base64"}, {Base64Hex: "base64_hex"}, {HexUpper: "hex_upper"}, {HexLower: "hex_lower"});
+
 impl PostgresSchema {
   pub fn from(schema_name: String, values: &Value) -> Result<PostgresSchema, ExpectedError> {
-    if !values.is_object() {
-      return Err(ExpectedError::TypeError(String::from("input values is not object type!")));
+    let problems = Self::validate(&schema_name, values);
+    if !problems.is_empty() {
+      return Err(ExpectedError::InvalidError(problems.join("\n")));
     }
     let map = values.as_object().unwrap();
     let raw_attributes = get_object(map, "attributes")?;
@@ -36,10 +72,7 @@ impl PostgresSchema {
     let mut attributes: Vec<Attribute> = Vec::new();
     for (key, value) in raw_attributes {
       let parsed_value = value.as_object().unwrap();
-      let size = match parsed_value.get("maxLength") {
-        None => None,
-        Some(size) => Some(size.as_u64().unwrap() as u32)
-      };
+      let size = parsed_value.get("maxLength").map(|size| size.as_u64().unwrap() as u32);
       let description = match parsed_value.get("description") {
         None => key.clone(),
         Some(description) => String::from(description.as_str().unwrap())
@@ -57,72 +90,275 @@ impl PostgresSchema {
           if v_str.len() > 1 && v_str.get(1).unwrap() != "null" {
             return Err(ExpectedError::InvalidError(String::from("second value of types must be null!")));
           }
-          (v_str.get(0).unwrap().clone(), true)
+          (v_str.first().unwrap().clone(), true)
         }
         Value::String(v) => (v.clone(), false),
         _ => return Err(ExpectedError::TypeError(String::from("type only can be string or array!")))
       };
+      let transform = match parsed_value.get("transform") {
+        None => None,
+        Some(transform) => {
+          let transform_str = transform.as_str().unwrap_or_default();
+          match Transform::find(transform_str) {
+            None => return Err(ExpectedError::InvalidError(format!("{} is not supported transform! attribute={}", transform_str, key))),
+            transform => transform,
+          }
+        }
+      };
+      let template = parsed_value.get("template").map(|template| String::from(template.as_str().unwrap()));
+      let items = match parsed_value.get("items") {
+        None => None,
+        Some(items) => {
+          let items_type = get_str(opt_to_result(items.as_object(), format!("items must be object! attribute={}", key))?, "type")?;
+          if items_type == "array" || items_type == "object" {
+            return Err(ExpectedError::InvalidError(format!("items type must be scalar type! attribute={}", key)));
+          }
+          Some(String::from(items_type))
+        }
+      };
 
       let attribute = Attribute {
         name: key.clone(),
         description,
+        transform,
+        template,
+        default: parsed_value.get("default").cloned(),
         _type,
+        items,
         max_length: size,
         nullable,
       };
+      let _ = attribute.column_type()?;
       attributes.push(attribute);
     }
 
-    let uniques = get_array(map, "uniques")?;
-    let indexes = get_array(map, "indexes")?;
-    let create_table = Self::create_table(schema_name.clone(), &attributes, uniques);
-    let create_index = Self::create_index(schema_name.clone(), indexes);
+    let uniques = Self::column_sets(get_array(map, "uniques")?);
+    let indexes = Self::column_sets(get_array(map, "indexes")?);
+    let versioned = map.get("versioned").and_then(|versioned| versioned.as_bool()).unwrap_or(false);
+    if versioned {
+      if uniques.is_empty() {
+        return Err(ExpectedError::InvalidError(format!("versioned schema requires uniques! schema_name={}", schema_name)));
+      }
+      if map.get("on_conflict").is_some() {
+        return Err(ExpectedError::InvalidError(format!("versioned schema cannot have on_conflict! schema_name={}", schema_name)));
+      }
+      attributes.push(Attribute::system("version"));
+      attributes.push(Attribute::system("valid_from"));
+    }
+    let create_table = Self::create_table(schema_name.clone(), &attributes, &uniques, versioned)?;
+    let create_index = Self::create_index(schema_name.clone(), &indexes);
     let insert_query = Self::insert_query(schema_name.clone(), &attributes);
-    let values_format = Self::values_format(&attributes);
+    let on_conflict = OnConflict::from(map.get("on_conflict"), &attributes, &uniques)
+      .map_err(|err| ExpectedError::InvalidError(format!("schema={}: {}", schema_name, err)))?;
+    let mut sinks = match map.get("sinks") {
+      None => vec!(String::from("postgres")),
+      Some(raw_sinks) => {
+        let raw_sinks = opt_to_result(raw_sinks.as_array(), format!("sinks must be array! schema_name={}", schema_name))?;
+        raw_sinks.iter().map(|sink| {
+          opt_to_result(sink.as_str(), format!("sinks must be array of string! schema_name={}", schema_name)).map(String::from)
+        }).collect::<Result<Vec<String>, ExpectedError>>()?
+      }
+    };
+    // postgres is written first, so the other sinks never get rows that postgres refused.
+    sinks.sort_by_key(|sink| sink != "postgres");
+    // the records a schema is mapped from, named by the message the task sends. defaults to the schema itself.
+    let source = map.get("source").and_then(|source| source.as_str()).map(String::from).unwrap_or(schema_name.clone());
+    let version = hex::encode(Sha256::digest(values.to_string().as_bytes()));
 
     Ok(PostgresSchema {
       schema_name: schema_name.clone(),
+      version,
       attributes,
+      indexes,
+      uniques,
       create_table,
       create_index,
       insert_query,
-      values_format,
+      on_conflict,
+      versioned,
+      sinks,
+      source,
     })
   }
 
-  fn create_table(schema_name: String, attributes: &Vec<Attribute>, uniques: &Vec<Value>) -> String {
+  pub fn validate(schema_name: &String, values: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let identifier = Regex::new(r"^[a-z_][a-z0-9_]*$").unwrap();
+    if !identifier.is_match(schema_name) {
+      problems.push(format!("schema={}: schema name must be lowercase letters, digits and underscores!", schema_name));
+    }
+    if RESERVED_TABLES.contains(&schema_name.as_str()) {
+      problems.push(format!("schema={}: schema name is reserved!", schema_name));
+    }
+    let map = match values.as_object() {
+      Some(map) => map,
+      None => {
+        problems.push(format!("schema={}: schema must be object!", schema_name));
+        return problems;
+      }
+    };
+    let versioned = match map.get("versioned") {
+      None => false,
+      Some(Value::Bool(versioned)) => *versioned,
+      Some(_) => {
+        problems.push(format!("schema={}: versioned must be boolean!", schema_name));
+        false
+      }
+    };
+
+    let mut reserved = vec!(format!("{}_id", schema_name));
+    if versioned {
+      reserved.extend(HISTORY_COLUMNS.iter().map(|name| String::from(*name)));
+    }
+    let mut column_names = Vec::new();
+    match map.get("attributes").and_then(|attributes| attributes.as_object()) {
+      None => problems.push(format!("schema={}: attributes must be object!", schema_name)),
+      Some(attributes) if attributes.is_empty() => problems.push(format!("schema={}: attributes must not be empty!", schema_name)),
+      Some(attributes) => {
+        for (name, attribute) in attributes.iter() {
+          if !identifier.is_match(name) {
+            problems.push(format!("schema={}, attribute={}: attribute name must be lowercase letters, digits and underscores!", schema_name, name));
+          }
+          if reserved.contains(name) {
+            problems.push(format!("schema={}, attribute={}: attribute name is reserved!", schema_name, name));
+          }
+          for problem in Attribute::validate(attribute) {
+            problems.push(format!("schema={}, attribute={}: {}", schema_name, name, problem));
+          }
+          column_names.push(name.clone());
+        }
+      }
+    }
+    if versioned {
+      column_names.extend(HISTORY_COLUMNS.iter().map(|name| String::from(*name)));
+    }
+
+    for key in ["indexes", "uniques"] {
+      let raw_sets = match map.get(key).and_then(|raw_sets| raw_sets.as_array()) {
+        Some(raw_sets) => raw_sets,
+        None => {
+          problems.push(format!("schema={}: {} must be array!", schema_name, key));
+          continue;
+        }
+      };
+      let mut column_sets: Vec<Vec<String>> = Vec::new();
+      for raw_set in raw_sets.iter() {
+        let column_set: Option<Vec<String>> = raw_set.as_array().and_then(|raw_set| {
+          raw_set.iter().map(|column| column.as_str().map(String::from)).collect()
+        });
+        let column_set = match column_set {
+          Some(column_set) if !column_set.is_empty() => column_set,
+          _ => {
+            problems.push(format!("schema={}: {} must be array of non-empty string arrays! value={}", schema_name, key, raw_set));
+            continue;
+          }
+        };
+        for column in column_set.iter() {
+          if !column_names.contains(column) {
+            problems.push(format!("schema={}: {} has unknown column! column={}", schema_name, key, column));
+          }
+        }
+        if column_sets.contains(&column_set) {
+          problems.push(format!("schema={}: {} has duplicate entry! columns={:?}", schema_name, key, column_set));
+        }
+        column_sets.push(column_set);
+      }
+    }
+
+    if map.get("source").is_some_and(|source| !source.is_string()) {
+      problems.push(format!("schema={}: source must be string!", schema_name));
+    }
+    if let Some(raw_sinks) = map.get("sinks") {
+      let sinks: Option<Vec<&str>> = raw_sinks.as_array().and_then(|raw_sinks| raw_sinks.iter().map(|sink| sink.as_str()).collect());
+      match sinks {
+        None => problems.push(format!("schema={}: sinks must be array of string!", schema_name)),
+        Some(sinks) => {
+          for (idx, sink) in sinks.iter().enumerate() {
+            if sinks[..idx].contains(sink) {
+              problems.push(format!("schema={}: sinks has duplicate entry! sink={}", schema_name, sink));
+            }
+          }
+        }
+      }
+    }
+    problems
+  }
+
+  fn column_sets(raw_sets: &[Value]) -> Vec<Vec<String>> {
+    raw_sets.iter().map(|raw_keys| {
+      raw_keys.as_array().unwrap().iter().map(|v| { String::from(v.as_str().unwrap()) }).collect()
+    }).collect()
+  }
+
+  fn create_table(schema_name: String, attributes: &[Attribute], uniques: &[Vec<String>], versioned: bool) -> Result<String, ExpectedError> {
     let mut query_line: Vec<String> = Vec::new();
     query_line.push(format!("{}_id serial8", schema_name));
     for attribute in attributes.iter() {
-      let converted_type = convert_type(attribute._type.clone()).unwrap();
-      if attribute.max_length.is_none() {
-        query_line.push(format!("{} {} {}", attribute.name, converted_type, Self::null_or_not(attribute.nullable)));
-      } else {
-        query_line.push(format!("{} {}({}) {}", attribute.name, converted_type, attribute.max_length.unwrap(), Self::null_or_not(attribute.nullable)));
-      }
+      query_line.push(attribute.column_definition()?);
+    }
+    if versioned {
+      query_line.push(String::from("valid_to bigint NULL"));
     }
     query_line.push(format!("CONSTRAINT {schema_name}_pk PRIMARY KEY ({schema_name}_id)", schema_name = schema_name));
 
-    for raw_keys in uniques.iter() {
-      let unique_vec: Vec<String> = raw_keys.as_array().unwrap().iter().map(|v| { String::from(v.as_str().unwrap()) }).collect();
-      let unique_name = format!("{}_{}_un", schema_name, unique_vec.join("_"));
-      query_line.push(format!("CONSTRAINT {} UNIQUE ({})", unique_name, unique_vec.join(", ")));
+    if !versioned {
+      for unique_vec in uniques.iter() {
+        query_line.push(format!("CONSTRAINT {} UNIQUE ({})", Self::unique_name(&schema_name, unique_vec, versioned), unique_vec.join(", ")));
+      }
     }
     let full_query = query_line.join(", ");
-    format!("CREATE TABLE {} ({})", schema_name, full_query)
+    Ok(format!("CREATE TABLE {} ({})", schema_name, full_query))
   }
 
-  fn create_index(schema_name: String, indexes: &Vec<Value>) -> Vec<String> {
+  fn create_index(schema_name: String, indexes: &[Vec<String>]) -> Vec<String> {
     let mut index_query = Vec::new();
-    for raw_keys in indexes.iter() {
-      let index_vec: Vec<String> = raw_keys.as_array().unwrap().iter().map(|v| { String::from(v.as_str().unwrap()) }).collect();
-      let index_name = format!("{}_{}_idx", schema_name, index_vec.join("_"));
-      index_query.push(format!("CREATE INDEX {} ON {} USING btree ({})", index_name, schema_name, index_vec.join(", ")));
+    for index_vec in indexes.iter() {
+      index_query.push(format!("CREATE INDEX {} ON {} USING btree ({})", Self::index_name(&schema_name, index_vec), schema_name, index_vec.join(", ")));
     }
     index_query
   }
 
-  fn insert_query(schema_name: String, attributes: &Vec<Attribute>) -> String {
+  pub fn index_name(schema_name: &str, index_vec: &[String]) -> String {
+    format!("{}_{}_idx", schema_name, index_vec.join("_"))
+  }
+
+  pub fn unique_name(schema_name: &str, unique_vec: &[String], versioned: bool) -> String {
+    if versioned {
+      format!("{}_{}_cur_un", schema_name, unique_vec.join("_"))
+    } else {
+      format!("{}_{}_un", schema_name, unique_vec.join("_"))
+    }
+  }
+
+  pub fn create_unique(&self, unique_vec: &[String]) -> String {
+    let unique_name = Self::unique_name(&self.schema_name, unique_vec, self.versioned);
+    if self.versioned {
+      format!("CREATE UNIQUE INDEX {} ON {} USING btree ({}) WHERE valid_to IS NULL", unique_name, self.schema_name, unique_vec.join(", "))
+    } else {
+      format!("ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({})", self.schema_name, unique_name, unique_vec.join(", "))
+    }
+  }
+
+  pub fn create_statements(&self) -> Vec<String> {
+    let mut statements = vec!(self.create_table.clone());
+    if self.versioned {
+      for unique_vec in self.uniques.iter() {
+        statements.push(self.create_unique(unique_vec));
+      }
+    }
+    statements.extend(self.create_index.iter().cloned());
+    statements
+  }
+
+  pub fn system_columns(&self) -> Vec<(String, String)> {
+    let mut system_columns = vec!((format!("{}_id", self.schema_name), String::from("serial8")));
+    if self.versioned {
+      system_columns.push((String::from("valid_to"), String::from("bigint NULL")));
+    }
+    system_columns
+  }
+
+  fn insert_query(schema_name: String, attributes: &[Attribute]) -> String {
     let mut column_vec = Vec::new();
     for attribute in attributes.iter() {
       column_vec.push(attribute.name.clone());
@@ -132,13 +368,123 @@ impl PostgresSchema {
     format!("INSERT INTO {} ({}) VALUES ", schema_name, columns)
   }
 
-  fn values_format(attributes: &Vec<Attribute>) -> String {
-    let mut value_vec = Vec::new();
-    for attribute in attributes.iter() {
-      value_vec.push(format!("${}$", attribute.description.clone()));
+  pub fn insert_statement(&self, row_count: usize) -> Result<String, ExpectedError> {
+    let mut rows = Vec::new();
+    for row in 0..row_count {
+      let mut placeholders = Vec::new();
+      for (idx, attribute) in self.attributes.iter().enumerate() {
+        let param_idx = row * self.attributes.len() + idx + 1;
+        if self.versioned {
+          placeholders.push(attribute.typed_placeholder(param_idx)?);
+        } else {
+          placeholders.push(attribute.placeholder(param_idx)?);
+        }
+      }
+      if self.versioned {
+        // the position of the row, which orders the versions of a key.
+        placeholders.push((row + 1).to_string());
+      }
+      rows.push(format!("({})", placeholders.join(", ")));
+    }
+    if self.versioned {
+      return Ok(self.versioned_insert(format!("VALUES {}", rows.join(", "))));
+    }
+    Ok(format!("{}{}{}", self.insert_query, rows.join(", "), self.on_conflict.clause()))
+  }
+
+  /// applies the rows of the source in the order of their last column, one version per row.
+  /// a row that equals the version before it is skipped, and each new version is valid until the next one of its key.
+  fn versioned_insert(&self, source: String) -> String {
+    let table = &self.schema_name;
+    let key = &self.uniques[0];
+    let columns = self.column_names();
+    let key_match = |alias: &str| -> String {
+      key.iter().map(|column| format!("{table}.{column} = {alias}.{column}", table = table, column = column, alias = alias)).collect::<Vec<String>>().join(" AND ")
+    };
+    let by_key = |alias: &str| -> String {
+      let partition: Vec<String> = key.iter().map(|column| format!("{}.{}", alias, column)).collect();
+      format!("OVER (PARTITION BY {} ORDER BY {}.seq)", partition.join(", "), alias)
+    };
+    let compared: Vec<&String> = self.attributes.iter().map(|attribute| &attribute.name).filter(|name| !key.contains(name) && !HISTORY_COLUMNS.contains(&name.as_str())).collect();
+    let (previous, changed_condition) = if compared.is_empty() {
+      (String::new(), format!("ordered.position = 1 AND {table}.{table}_id IS NULL", table = table))
+    } else {
+      let current: Vec<String> = compared.iter().map(|name| format!("{}.{}", table, name)).collect();
+      let incoming: Vec<String> = compared.iter().map(|name| format!("incoming.{}", name)).collect();
+      let ordered: Vec<String> = compared.iter().map(|name| format!("ordered.{}", name)).collect();
+      (format!(", lag(ROW({})) {} AS previous", incoming.join(", "), by_key("incoming")),
+       format!("CASE WHEN ordered.position = 1 THEN {table}.{table}_id IS NULL OR ({}) IS DISTINCT FROM ({}) ELSE ordered.previous IS DISTINCT FROM ROW({}) END",
+               current.join(", "), ordered.join(", "), ordered.join(", "), table = table))
+    };
+    let inserted: Vec<String> = self.attributes.iter().map(|attribute| {
+      if attribute.name == "version" {
+        String::from("CASE WHEN version < 0 THEN COALESCE(current_version, 0) + change ELSE version END")
+      } else {
+        attribute.name.clone()
+      }
+    }).collect();
+
+    // data modifying ctes run concurrently with the insert, so the unique index on current rows could see a new version before the old one is closed.
+    // the insert reads the count of closed rows, which makes postgres finish closing them before it inserts anything.
+    format!("WITH incoming ({columns}, seq) AS ({source}), \
+      ordered AS (SELECT incoming.*, row_number() {incoming_order} AS position{previous} FROM incoming), \
+      changed AS (SELECT ordered.*, {table}.version AS current_version, row_number() {ordered_order} AS change, lead(ordered.valid_from) {ordered_order} AS next_from \
+      FROM ordered LEFT JOIN {table} ON {ordered_match} AND {table}.valid_to IS NULL WHERE {changed_condition}), \
+      closed AS (UPDATE {table} SET valid_to = changed.valid_from FROM changed WHERE {changed_match} AND changed.change = 1 AND {table}.valid_to IS NULL RETURNING {table}.{table}_id) \
+      INSERT INTO {table} ({columns}, valid_to) SELECT {inserted}, next_from FROM changed WHERE (SELECT count(*) FROM closed) >= 0",
+      columns = columns,
+      source = source,
+      incoming_order = by_key("incoming"),
+      previous = previous,
+      table = table,
+      ordered_order = by_key("ordered"),
+      ordered_match = key_match("ordered"),
+      changed_condition = changed_condition,
+      changed_match = key_match("changed"),
+      inserted = inserted.join(", "))
+  }
+
+  pub fn column_names(&self) -> String {
+    self.attributes.iter().map(|attribute| attribute.name.clone()).collect::<Vec<String>>().join(", ")
+  }
+
+  pub fn staging_name(&self) -> String {
+    format!("{}_staging", self.schema_name)
+  }
+
+  pub fn create_staging(&self) -> Result<String, ExpectedError> {
+    let mut query_line = Vec::new();
+    for attribute in self.attributes.iter() {
+      query_line.push(format!("{} {}", attribute.name, attribute.staging_type()?));
+    }
+    query_line.push(format!("{}_id bigserial", self.staging_name()));
+    Ok(format!("CREATE TEMP TABLE IF NOT EXISTS {} ({}) ON COMMIT DELETE ROWS", self.staging_name(), query_line.join(", ")))
+  }
+
+  /// an update merges rows in one statement, so only the last copied row of each target is kept.
+  /// versioned rows are applied in the order they were copied.
+  pub fn merge_staging(&self) -> Result<String, ExpectedError> {
+    let mut select_line = Vec::new();
+    for attribute in self.attributes.iter() {
+      select_line.push(attribute.staging_select()?);
+    }
+    if self.versioned {
+      return Ok(self.versioned_insert(format!("SELECT {}, {staging}_id FROM {staging}", select_line.join(", "), staging = self.staging_name())));
+    }
+    let source = if self.on_conflict.updates() {
+      format!("SELECT DISTINCT ON ({target}) {} FROM {staging} ORDER BY {target}, {staging}_id DESC", select_line.join(", "), target = self.on_conflict.target.join(", "), staging = self.staging_name())
+    } else {
+      format!("SELECT {} FROM {}", select_line.join(", "), self.staging_name())
+    };
+    Ok(format!("INSERT INTO {} ({}) {}{}", self.schema_name, self.column_names(), source, self.on_conflict.clause()))
+  }
+
+  /// adds the values the daemon fills in itself, which are the message version and the height a version of a versioned row is valid from.
+  pub fn system_values(&self, values: &mut Map<String, Value>, version: i64, height: u64) {
+    values.insert(String::from("version"), Value::from(version));
+    if self.versioned {
+      values.insert(String::from("valid_from"), Value::from(height));
     }
-    let values = value_vec.join(", ");
-    format!("({})", values)
   }
 
   fn null_or_not(nullable: bool) -> String {
@@ -149,3 +495,217 @@ impl PostgresSchema {
     }
   }
 }
+
+impl OnConflict {
+  pub fn from(raw_on_conflict: Option<&Value>, attributes: &[Attribute], uniques: &[Vec<String>]) -> Result<OnConflict, ExpectedError> {
+    let (raw_action, raw_target, raw_columns) = match raw_on_conflict {
+      None => ("fail", None, None),
+      Some(Value::String(action)) => (action.as_str(), None, None),
+      Some(Value::Object(on_conflict)) => (get_str(on_conflict, "action")?, on_conflict.get("target"), on_conflict.get("columns")),
+      Some(_) => return Err(ExpectedError::TypeError(String::from("on_conflict only can be string or object!"))),
+    };
+    let action = opt_to_result(ConflictAction::find(raw_action), format!("{} is not supported on_conflict action! only ignore, update and fail are supported.", raw_action))?;
+    if action != ConflictAction::Update {
+      return Ok(OnConflict { action, target: Vec::new(), columns: Vec::new() });
+    }
+
+    let target = match raw_target {
+      None => opt_to_result(uniques.first(), String::from("on_conflict update requires uniques!"))?.clone(),
+      Some(raw_target) => Self::string_vec(raw_target, "target")?,
+    };
+    if !uniques.contains(&target) {
+      return Err(ExpectedError::InvalidError(format!("on_conflict target must be one of uniques! target={:?}", target)));
+    }
+    let columns = match raw_columns {
+      None => attributes.iter().map(|attribute| attribute.name.clone()).filter(|name| !target.contains(name)).collect(),
+      Some(raw_columns) => Self::string_vec(raw_columns, "columns")?,
+    };
+    for column in columns.iter() {
+      if !attributes.iter().any(|attribute| &attribute.name == column) {
+        return Err(ExpectedError::InvalidError(format!("on_conflict column is not in attributes! column={}", column)));
+      }
+    }
+    Ok(OnConflict { action, target, columns })
+  }
+
+  fn string_vec(raw_values: &Value, name: &str) -> Result<Vec<String>, ExpectedError> {
+    let raw_values = opt_to_result(raw_values.as_array(), format!("on_conflict {} must be array!", name))?;
+    raw_values.iter().map(|value| {
+      opt_to_result(value.as_str(), format!("on_conflict {} must be array of string!", name)).map(String::from)
+    }).collect()
+  }
+
+  pub fn updates(&self) -> bool {
+    self.action == ConflictAction::Update && !self.columns.is_empty()
+  }
+
+  pub fn clause(&self) -> String {
+    match self.action {
+      ConflictAction::Ignore => String::from(" ON CONFLICT DO NOTHING"),
+      ConflictAction::Update if self.columns.is_empty() => format!(" ON CONFLICT ({}) DO NOTHING", self.target.join(", ")),
+      ConflictAction::Update => {
+        let updates: Vec<String> = self.columns.iter().map(|column| format!("{column} = EXCLUDED.{column}", column = column)).collect();
+        format!(" ON CONFLICT ({}) DO UPDATE SET {}", self.target.join(", "), updates.join(", "))
+      }
+      ConflictAction::Fail => String::new(),
+    }
+  }
+}
+
+impl Attribute {
+  fn validate(raw_attribute: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let attribute = match raw_attribute.as_object() {
+      Some(attribute) => attribute,
+      None => return vec!(String::from("attribute must be object!")),
+    };
+
+    let types: Vec<Option<&str>> = match attribute.get("type") {
+      None => return vec!(String::from("attribute must include type!")),
+      Some(Value::String(_type)) => vec!(Some(_type.as_str())),
+      Some(Value::Array(types)) => types.iter().map(|_type| _type.as_str()).collect(),
+      Some(_) => return vec!(String::from("type only can be string or array!")),
+    };
+    let _type = match types.as_slice() {
+      [Some(_type)] | [Some(_type), Some("null")] => *_type,
+      _ => return vec!(String::from("type must be a type name, or an array of a type name and null!")),
+    };
+    if let Err(err) = convert_type(String::from(_type)) {
+      problems.push(err.to_string());
+    }
+
+    let items = match attribute.get("items") {
+      None => None,
+      Some(_) if _type != "array" => {
+        problems.push(String::from("items is only allowed for array type!"));
+        None
+      }
+      Some(items) => match items.as_object().and_then(|items| items.get("type")).and_then(|items_type| items_type.as_str()) {
+        None => {
+          problems.push(String::from("items must be object with string type!"));
+          None
+        }
+        Some("array") | Some("object") => {
+          problems.push(String::from("items type must be scalar type!"));
+          None
+        }
+        Some(items_type) => {
+          if let Err(err) = convert_type(String::from(items_type)) {
+            problems.push(format!("items {}", err));
+          }
+          Some(items_type)
+        }
+      }
+    };
+
+    if let Some(max_length) = attribute.get("maxLength") {
+      let sized_type = items.unwrap_or(_type);
+      let limit = match sized_type {
+        "string" | "array" => Some(MAX_VARCHAR_LENGTH),
+        "numeric" => Some(MAX_NUMERIC_PRECISION),
+        _ => None,
+      };
+      match (max_length.as_u64(), limit) {
+        (_, None) => problems.push(format!("maxLength is not allowed for {} type!", sized_type)),
+        (Some(max_length), Some(limit)) if max_length > 0 && max_length <= limit => {}
+        (_, Some(limit)) => problems.push(format!("maxLength must be integer between 1 and {}! maxLength={}", limit, max_length)),
+      }
+    }
+
+    for key in ["description", "template"] {
+      if attribute.get(key).is_some_and(|value| !value.is_string()) {
+        problems.push(format!("{} must be string!", key));
+      }
+    }
+    if attribute.contains_key("description") && attribute.contains_key("template") {
+      problems.push(String::from("description and template cannot be used together!"));
+    }
+    if let Some(transform) = attribute.get("transform") {
+      if transform.as_str().and_then(Transform::find).is_none() {
+        problems.push(format!("{} is not supported transform!", transform));
+      }
+    }
+    for key in attribute.keys() {
+      if !["type", "description", "maxLength", "items", "transform", "template", "default"].contains(&key.as_str()) {
+        problems.push(format!("{} is unknown attribute field!", key));
+      }
+    }
+    problems
+  }
+
+  fn system(name: &str) -> Self {
+    Attribute {
+      name: String::from(name),
+      description: String::from(name),
+      transform: None,
+      template: None,
+      default: None,
+      _type: String::from("integer"),
+      items: None,
+      max_length: None,
+      nullable: false,
+    }
+  }
+
+  pub fn typed_placeholder(&self, idx: usize) -> Result<String, ExpectedError> {
+    match self.text_bound_type() {
+      Some(_) => self.placeholder(idx),
+      None => Ok(format!("${}::{}", idx, self.column_type()?)),
+    }
+  }
+
+  pub fn placeholder(&self, idx: usize) -> Result<String, ExpectedError> {
+    let placeholder = match self.text_bound_type() {
+      Some(text_type) => format!("${}::{}::{}", idx, text_type, self.column_type()?),
+      None => format!("${}", idx),
+    };
+    Ok(placeholder)
+  }
+
+  pub fn staging_type(&self) -> Result<String, ExpectedError> {
+    match self.text_bound_type() {
+      Some(text_type) => Ok(String::from(text_type)),
+      None => self.column_type(),
+    }
+  }
+
+  pub fn staging_select(&self) -> Result<String, ExpectedError> {
+    let select = match self.text_bound_type() {
+      Some(_) => format!("{}::{}", self.name, self.column_type()?),
+      None => self.name.clone(),
+    };
+    Ok(select)
+  }
+
+  fn text_bound_type(&self) -> Option<&str> {
+    if self._type == "numeric" {
+      Some("text")
+    } else if self._type == "array" && self.items == Some(String::from("numeric")) {
+      Some("text[]")
+    } else {
+      None
+    }
+  }
+
+  pub fn column_definition(&self) -> Result<String, ExpectedError> {
+    Ok(format!("{} {} {}", self.name, self.column_type()?, PostgresSchema::null_or_not(self.nullable)))
+  }
+
+  pub fn column_type(&self) -> Result<String, ExpectedError> {
+    let is_typed_array = self._type == "array" && self.items.is_some();
+    let base_type = if is_typed_array {
+      convert_type(self.items.clone().unwrap())?
+    } else {
+      convert_type(self._type.clone())?
+    };
+    let sized_type = match self.max_length {
+      None => base_type,
+      Some(max_length) => format!("{}({})", base_type, max_length),
+    };
+    if is_typed_array {
+      Ok(format!("{}[]", sized_type))
+    } else {
+      Ok(sized_type)
+    }
+  }
+}
@@ -2,7 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 use crate::enumeration;
-use crate::libs::serde::{get_str, get_string, get_string_vec, get_u64};
+use crate::error::error::ExpectedError;
+use crate::libs::serde::{filter, get_array, get_str, get_string, get_string_vec, get_u64};
 use crate::types::enumeration::Enumeration;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -13,6 +14,8 @@ pub struct SyncState {
   pub chain_id: String,
   pub from_idx: u64,
   pub sync_idx: u64,
+  #[serde(default)]
+  pub to_idx: Option<u64>,
   pub endpoints: Vec<String>,
   pub endpoint_idx: u16,
   pub filter: String,
@@ -32,6 +35,7 @@ impl SyncState {
       chain_id,
       from_idx: get_u64(params, "from_idx").unwrap(),
       sync_idx: get_u64(params, "from_idx").unwrap(),
+      to_idx: get_u64(params, "to_idx").ok(),
       endpoints: get_string_vec(params, "endpoints"),
       endpoint_idx: 0,
       filter: get_string(params, "filter").unwrap(),
@@ -40,6 +44,26 @@ impl SyncState {
     }
   }
 
+  /// checks the fields `new` unwraps, so a broken sync file is reported instead of panicking.
+  pub fn validate(params: &Map<String, Value>) -> Result<(), ExpectedError> {
+    for key in ["sync_type", "chain_name", "chain_id", "filter"] {
+      let _ = get_str(params, key)?;
+    }
+    let from_idx = get_u64(params, "from_idx")?;
+    if let Some(to_idx) = params.get("to_idx").filter(|to_idx| !to_idx.is_null()) {
+      let to_idx = to_idx.as_u64().ok_or(ExpectedError::TypeError(String::from("to_idx must be integer!")))?;
+      if to_idx < from_idx {
+        return Err(ExpectedError::InvalidError(format!("to_idx must not be less than from_idx! from_idx={}, to_idx={}", from_idx, to_idx)));
+      }
+    }
+    let endpoints = get_array(params, "endpoints")?;
+    if endpoints.is_empty() || endpoints.iter().any(|endpoint| !endpoint.is_string()) {
+      return Err(ExpectedError::InvalidError(String::from("endpoints must be non empty array of string!")));
+    }
+    let _ = filter(&Map::new(), get_string(params, "filter")?)?;
+    Ok(())
+  }
+
   pub fn from(params: &Map<String, Value>) -> Self {
     SyncState {
       sync_id: get_string(params, "sync_id").unwrap(),
@@ -48,16 +72,21 @@ impl SyncState {
       chain_id: get_string(params, "chain_id").unwrap(),
       from_idx: get_u64(params, "from_idx").unwrap(),
       sync_idx: get_u64(params, "sync_idx").unwrap(),
+      to_idx: get_u64(params, "to_idx").ok(),
       endpoints: get_string_vec(params, "endpoints"),
       endpoint_idx: get_u64(params, "endpoint_idx").unwrap() as u16,
       filter: get_string(params, "filter").unwrap(),
-      status: SyncStatus::find(get_str(params, "status").unwrap()).unwrap(),
+      // a pause only holds while the postgres sink of the process that paused it is failing.
+      status: match SyncStatus::find(get_str(params, "status").unwrap()).unwrap() {
+        SyncStatus::Paused => SyncStatus::Working,
+        status => status,
+      },
       error_msg: get_string(params, "error_msg").unwrap(),
     }
   }
 
   pub fn is_workable(&self) -> bool {
-    vec!(SyncStatus::Working).contains(&self.status)
+    self.status == SyncStatus::Working
   }
 
   pub fn handle_error(&mut self, error_msg: String) {
@@ -74,12 +103,88 @@ impl SyncState {
     self.endpoints[idx].clone()
   }
 
-  pub fn next_idx(&mut self) { self.sync_idx += 1; }
+  pub fn next_idx(&mut self) {
+    self.sync_idx += 1;
+    if self.is_completed() {
+      self.status = SyncStatus::Completed;
+    }
+  }
+
+  pub fn is_completed(&self) -> bool {
+    self.to_idx.is_some_and(|to_idx| self.sync_idx > to_idx)
+  }
 
   pub fn status(&mut self, status: SyncStatus) { self.status = status; }
 
   pub fn get_filter(&self) -> String { self.filter.clone() }
 }
 
-enumeration!(SyncStatus; {Working: "Working"}, {Stopped: "Stopped"}, {Error: "Error"});
-enumeration!(SyncMethod; {Start: "start"}, {Stop: "stop"});
+enumeration!(SyncStatus; {Working: "Working"}, {Stopped: "Stopped"}, {Error: "Error"}, {Completed: "Completed"}, {Paused: "Paused"});
+enumeration!(SyncMethod; {Start: "start"}, {Stop: "stop"}, {Pause: "pause"}, {Resume: "resume"});
+
+#[cfg(test)]
+mod sync {
+  use serde_json::json;
+
+  use crate::libs::sync_helper::message_handler;
+  use crate::types::sync::{SyncState, SyncStatus};
+
+  #[test]
+  fn validate_test() {
+    let mut sync_file = json!({
+      "sync_type": "block_sync",
+      "chain_name": "cosmos",
+      "chain_id": "cosmoshub-4",
+      "from_idx": 1,
+      "endpoints": [ "https://lcd.cosmos.network" ],
+      "filter": "proposer_address=57713BB7421C7FEB381B863FC87DED5E829AA961"
+    });
+    assert!(SyncState::validate(sync_file.as_object().unwrap()).is_ok());
+    sync_file["from_idx"] = json!("1");
+    assert!(SyncState::validate(sync_file.as_object().unwrap()).is_err());
+    sync_file["from_idx"] = json!(1);
+    sync_file["endpoints"] = json!([]);
+    assert!(SyncState::validate(sync_file.as_object().unwrap()).is_err());
+    sync_file["endpoints"] = json!([ "https://lcd.cosmos.network" ]);
+    sync_file["filter"] = json!("proposer_address");
+    assert!(SyncState::validate(sync_file.as_object().unwrap()).is_err());
+    sync_file["filter"] = json!("");
+    sync_file["to_idx"] = json!(0);
+    assert!(SyncState::validate(sync_file.as_object().unwrap()).is_err());
+  }
+
+  #[test]
+  fn completed_test() {
+    let sync_file = json!({
+      "sync_type": "block_sync", "chain_name": "cosmos", "chain_id": "cosmoshub-4", "from_idx": 1, "to_idx": 2,
+      "endpoints": [ "https://lcd.cosmos.network" ], "filter": ""
+    });
+    let mut sync_state = SyncState::new(sync_file.as_object().unwrap());
+    sync_state.next_idx();
+    assert!(sync_state.is_workable());
+    sync_state.next_idx();
+    assert_eq!(sync_state.status, SyncStatus::Completed);
+    assert!(!sync_state.is_workable());
+  }
+
+  #[test]
+  fn pause_test() {
+    let sync_file = json!({
+      "sync_type": "block_sync", "chain_name": "cosmos", "chain_id": "cosmoshub-4", "from_idx": 1,
+      "endpoints": [ "https://lcd.cosmos.network" ], "filter": ""
+    });
+    let mut sync_state = SyncState::new(sync_file.as_object().unwrap());
+    message_handler(json!({"method": "pause"}), &mut sync_state).unwrap();
+    assert_eq!(sync_state.status, SyncStatus::Paused);
+    message_handler(json!({"method": "start"}), &mut sync_state).unwrap();
+    assert_eq!(sync_state.status, SyncStatus::Paused);
+    let saved = serde_json::to_value(&sync_state).unwrap();
+    assert_eq!(SyncState::from(saved.as_object().unwrap()).status, SyncStatus::Working);
+    message_handler(json!({"method": "resume"}), &mut sync_state).unwrap();
+    assert!(sync_state.is_workable());
+
+    message_handler(json!({"method": "stop"}), &mut sync_state).unwrap();
+    message_handler(json!({"method": "resume"}), &mut sync_state).unwrap();
+    assert_eq!(sync_state.status, SyncStatus::Stopped);
+  }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs::email::parse_mailboxes;
+use crate::libs::serde::{filter, get_str, get_string, get_string_vec};
+use crate::plugin::slack::SlackMsgLevel;
+use crate::types::enumeration::Enumeration;
+
+const CHANNELS: [&str; 3] = ["slack", "email", "webhook"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Watchlist {
+  pub name: String,
+  pub schema: String,
+  pub condition: String,
+  pub level: String,
+  pub channels: Vec<String>,
+  pub email_to: String,
+}
+
+impl Watchlist {
+  pub fn from(params: &Map<String, Value>) -> Result<Self, ExpectedError> {
+    let name = get_string(params, "name")?;
+    let channels = match params.get("channels") {
+      Some(Value::Array(_)) => get_string_vec(params, "channels"),
+      _ => return Err(ExpectedError::InvalidError(format!("watchlist channels must be array of string! watchlist={}", name))),
+    };
+    if channels.is_empty() {
+      return Err(ExpectedError::InvalidError(format!("watchlist needs at least one channel! watchlist={}", name)));
+    }
+    if let Some(channel) = channels.iter().find(|channel| !CHANNELS.contains(&channel.as_str())) {
+      return Err(ExpectedError::InvalidError(format!("{} is not supported watchlist channel! use slack, email or webhook. watchlist={}", channel, name)));
+    }
+    let email_to = get_string(params, "email_to").unwrap_or_default();
+    if channels.iter().any(|channel| channel == "email") && parse_mailboxes(email_to.as_str())?.is_empty() {
+      return Err(ExpectedError::InvalidError(format!("email channel needs email_to! watchlist={}", name)));
+    }
+    let level = get_string(params, "level").unwrap_or(SlackMsgLevel::Info.value());
+    if SlackMsgLevel::find(level.as_str()).is_none() {
+      return Err(ExpectedError::InvalidError(format!("{} is not supported watchlist level! watchlist={}", level, name)));
+    }
+    let condition = get_string(params, "condition")?;
+    // parses the condition once, so a broken condition is rejected before it is stored.
+    let _ = filter(&Map::new(), condition.clone()).map_err(|err| ExpectedError::InvalidError(format!("invalid watchlist condition! watchlist={}, error={}", name, err)))?;
+    Ok(Watchlist {
+      schema: get_str(params, "schema").map(String::from).unwrap_or(String::from("cosmos_tx")),
+      condition,
+      level,
+      channels,
+      email_to,
+      name,
+    })
+  }
+
+  pub fn matches(&self, schema: &str, record: &Map<String, Value>) -> Result<bool, ExpectedError> {
+    if self.schema != schema {
+      return Ok(false);
+    }
+    filter(record, self.condition.clone())
+  }
+}
+
+/// copies the record with its tx events grouped as `events.{type}.{attribute}` lists of values.
+pub fn with_events(record: &Map<String, Value>) -> Map<String, Value> {
+  let mut events = Map::new();
+  let logged_events = record.get("logs").and_then(|logs| logs.as_array()).into_iter().flatten()
+    .filter_map(|log| log.get("events").and_then(|events| events.as_array())).flatten();
+  let top_events = record.get("events").and_then(|events| events.as_array()).into_iter().flatten();
+  for event in logged_events.chain(top_events) {
+    let event_type = match event.get("type").and_then(|event_type| event_type.as_str()) {
+      Some(event_type) => event_type,
+      None => continue,
+    };
+    let attributes = events.entry(event_type).or_insert_with(|| Value::Object(Map::new())).as_object_mut().unwrap();
+    for attribute in event.get("attributes").and_then(|attributes| attributes.as_array()).into_iter().flatten() {
+      if let (Some(key), Some(value)) = (attribute.get("key").and_then(|key| key.as_str()), attribute.get("value")) {
+        let values = attributes.entry(key).or_insert_with(|| Value::Array(Vec::new())).as_array_mut().unwrap();
+        if !values.contains(value) {
+          values.push(value.clone());
+        }
+      }
+    }
+  }
+  let mut record = record.clone();
+  if !events.is_empty() {
+    record.insert(String::from("events"), Value::Object(events));
+  }
+  record
+}
+
+#[cfg(test)]
+mod watch {
+  use serde_json::json;
+
+  use crate::types::watch::{Watchlist, with_events};
+
+  #[test]
+  fn watchlist_test() {
+    let params = json!({
+      "name": "large_delegation",
+      "condition": "events.delegate.validator=cosmosvaloper1ours & events.delegate.amount>=1000000000",
+      "channels": [ "slack", "webhook" ]
+    });
+    let watchlist = Watchlist::from(params.as_object().unwrap()).unwrap();
+    assert_eq!(watchlist.schema, "cosmos_tx");
+    assert_eq!(watchlist.level, "info");
+
+    let tx = json!({
+      "txhash": "ABC",
+      "logs": [ { "events": [
+        { "type": "delegate", "attributes": [ { "key": "validator", "value": "cosmosvaloper1ours" }, { "key": "amount", "value": "5000000000uatom" } ] },
+        { "type": "message", "attributes": [ { "key": "sender", "value": "cosmos1someone" } ] }
+      ] } ]
+    });
+    let record = with_events(tx.as_object().unwrap());
+    assert_eq!(record["events"]["message"]["sender"], json!([ "cosmos1someone" ]));
+    assert!(watchlist.matches("cosmos_tx", &record).unwrap());
+    assert!(!watchlist.matches("cosmos_block", &record).unwrap());
+
+    let invalid = json!({ "name": "invalid", "condition": "events.transfer.recipient", "channels": [ "slack" ] });
+    assert!(Watchlist::from(invalid.as_object().unwrap()).is_err());
+    let no_email = json!({ "name": "no_email", "condition": "code=0", "channels": [ "email" ] });
+    assert!(Watchlist::from(no_email.as_object().unwrap()).is_err());
+  }
+}
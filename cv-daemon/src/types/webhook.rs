@@ -0,0 +1,65 @@
+use serde_json::{Map, Value};
+
+use crate::error::error::ExpectedError;
+use crate::libs::opt::opt_to_result;
+use crate::libs::serde::get_string;
+
+#[derive(Clone, Debug)]
+pub struct WebhookTarget {
+  pub name: String,
+  pub url: String,
+  pub secret: Option<String>,
+  pub schemas: Vec<String>,
+  pub batch: bool,
+}
+
+impl WebhookTarget {
+  pub fn from(params: &Map<String, Value>) -> Result<Self, ExpectedError> {
+    let name = get_string(params, "name")?;
+    Ok(WebhookTarget {
+      url: get_string(params, "url")?,
+      secret: get_string(params, "secret").ok(),
+      schemas: Self::string_vec(&name, params, "schemas")?,
+      batch: params.get("batch").and_then(|batch| batch.as_bool()).unwrap_or(true),
+      name,
+    })
+  }
+
+  fn string_vec(name: &String, params: &Map<String, Value>, key: &str) -> Result<Vec<String>, ExpectedError> {
+    match params.get(key) {
+      None => Ok(Vec::new()),
+      Some(Value::Array(items)) => items.iter().map(|item| {
+        opt_to_result(item.as_str().map(String::from), format!("webhook {} must be array of string! target={}", key, name))
+      }).collect(),
+      Some(_) => Err(ExpectedError::InvalidError(format!("webhook {} must be array of string! target={}", key, name))),
+    }
+  }
+
+  pub fn is_routed(&self, schema_name: &str) -> bool {
+    self.schemas.is_empty() || self.schemas.iter().any(|schema| schema == schema_name)
+  }
+}
+
+#[cfg(test)]
+mod webhook {
+  use serde_json::json;
+
+  use crate::types::webhook::WebhookTarget;
+
+  #[test]
+  fn from_test() {
+    let params = json!({ "name": "indexer", "url": "http://localhost:8080/webhook", "schemas": [ "cosmos_block" ] });
+    let target = WebhookTarget::from(params.as_object().unwrap()).unwrap();
+    assert!(target.is_routed("cosmos_block"));
+    assert!(!target.is_routed("cosmos_tx"));
+    assert!(target.batch);
+
+    let params = json!({ "name": "indexer", "url": "http://localhost:8080/webhook" });
+    assert!(WebhookTarget::from(params.as_object().unwrap()).unwrap().is_routed("cosmos_tx"));
+
+    let params = json!({ "name": "indexer", "url": "http://localhost:8080/webhook", "schemas": "cosmos_block" });
+    assert!(WebhookTarget::from(params.as_object().unwrap()).is_err());
+    let params = json!({ "name": "indexer", "url": "http://localhost:8080/webhook", "schemas": [ 1 ] });
+    assert!(WebhookTarget::from(params.as_object().unwrap()).is_err());
+  }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use cv_daemon::error::error::ExpectedError;
+use cv_daemon::libs::sink::FileSink;
+use cv_daemon::libs::sync_helper::error_handler;
+use cv_daemon::plugin::block_sync::BlockSync;
+use cv_daemon::plugin::postgres::{Postgres, Sinks};
+use cv_daemon::types::channel::MultiSender;
+use cv_daemon::types::sync::SyncState;
+use cv_testkit::{DaemonDir, DaemonOutput, EphemeralPostgres, Fault, fixtures_dir, MockLcd};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+
+const DAEMON: &str = env!("CARGO_BIN_EXE_cv-daemon");
+const FROM: u64 = 7135430;
+const TO: u64 = 7135434;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Harness {
+  postgres: EphemeralPostgres,
+  lcd: MockLcd,
+  dir: DaemonDir,
+}
+
+/// the tests with a harness need postgres binaries, so they are ignored by default and run with `cargo test -- --ignored`.
+fn harness() -> Harness {
+  let postgres = EphemeralPostgres::start().unwrap_or_else(|err| panic!("failed to start postgres! {}", err));
+  let lcd = MockLcd::start();
+  lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  let dir = DaemonDir::new(&postgres, &Path::new(env!("CARGO_MANIFEST_DIR")).join("schema")).unwrap();
+  Harness { postgres, lcd, dir }
+}
+
+impl Harness {
+  fn backfill(&self, endpoints: Vec<String>, filter: &str) -> DaemonOutput {
+    let sync = json!({
+      "sync_type": "block_sync",
+      "chain_name": "cosmos",
+      "chain_id": "cosmoshub-4",
+      "from_idx": FROM,
+      "endpoints": endpoints,
+      "endpoint_idx": 0,
+      "filter": filter
+    });
+    let sync_file = self.dir.write_sync("block_sync.json", &sync).unwrap();
+    let (from, to) = (FROM.to_string(), TO.to_string());
+    let output = self.dir.run(DAEMON, &["backfill", "--sync", sync_file.as_str(), "--from", from.as_str(), "--to", to.as_str()], TIMEOUT).unwrap();
+    assert_eq!(output.code, Some(0), "stdout:\n{}\nstderr:\n{}", output.stdout, output.stderr);
+    assert!(output.stdout.contains("backfill completed!"), "{}", output.stdout);
+    output
+  }
+
+  fn count(&self, table: &str) -> i64 {
+    self.postgres.count(table).unwrap()
+  }
+}
+
+/// runs the sync of the block task against the lcd without the daemon, and keeps what it sends to postgres.
+struct Task {
+  sync_state: SyncState,
+  senders: MultiSender,
+  postgres: broadcast::Receiver<Value>,
+  runtime: tokio::runtime::Runtime,
+}
+
+impl Task {
+  fn new(endpoints: Vec<String>, filter: &str) -> Self {
+    let sync = json!({
+      "sync_type": "block_sync",
+      "chain_name": "cosmos",
+      "chain_id": "cosmoshub-4",
+      "from_idx": FROM,
+      "to_idx": TO,
+      "endpoints": endpoints,
+      "filter": filter
+    });
+    let (sender, postgres) = broadcast::channel(1000);
+    let senders = MultiSender::from(HashMap::from([(String::from("postgres"), sender)]));
+    Task { sync_state: SyncState::new(sync.as_object().unwrap()), senders, postgres, runtime: tokio::runtime::Runtime::new().unwrap() }
+  }
+
+  /// syncs one height like the block task does, and moves to the next height on success.
+  fn sync(&mut self) -> Result<(), ExpectedError> {
+    self.runtime.block_on(BlockSync::sync(&self.sync_state, &self.senders, None, None, false))?;
+    self.sync_state.next_idx();
+    Ok(())
+  }
+
+  fn sync_all(&mut self) {
+    while !self.sync_state.is_completed() {
+      match self.sync() {
+        Ok(_) => {}
+        Err(ExpectedError::FilterError(err)) => error_handler(ExpectedError::FilterError(err), &mut self.sync_state, &self.senders),
+        Err(err) => panic!("sync failed! height={}, error={}", self.sync_state.sync_idx, err),
+      }
+    }
+  }
+
+  /// writes the messages sent to postgres through the schemas into a file sink, and returns the rows of every schema.
+  fn rows(&mut self, dir: &Path) -> HashMap<String, Vec<Value>> {
+    let mut schema_map = Postgres::load_schema().unwrap();
+    for schema in schema_map.values_mut() {
+      schema.sinks = vec!(String::from("file"));
+    }
+    let mut sinks: Sinks = HashMap::new();
+    sinks.insert(String::from("file"), Box::new(FileSink::new(dir.display().to_string(), 100000)));
+    while let Ok(msg) = self.postgres.try_recv() {
+      Postgres::write(&mut sinks, &schema_map, &self.senders, msg, &mut Vec::new());
+    }
+    sinks.get_mut("file").unwrap().flush().unwrap();
+
+    let paths = FileSink::new(dir.display().to_string(), 100000);
+    schema_map.keys().map(|schema_name| {
+      let rows = fs::read_to_string(paths.file_path(schema_name, FROM / 100000 * 100000)).unwrap_or_default()
+        .lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+      (schema_name.clone(), rows)
+    }).collect()
+  }
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+  std::env::temp_dir().join(format!("cv-block-sync-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn sync_rows_test() {
+  let lcd = MockLcd::start();
+  lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  let mut task = Task::new(vec!(lcd.url()), "");
+  task.sync_all();
+
+  let blocks: Vec<String> = lcd.requests().into_iter().filter(|request| request.starts_with("/blocks/")).collect();
+  assert_eq!(blocks, (FROM..=TO).map(|height| format!("/blocks/{}", height)).collect::<Vec<String>>());
+  // txs are only requested for blocks that have them.
+  let txs: Vec<String> = lcd.requests().into_iter().filter(|request| request.contains("cosmos/tx")).collect();
+  assert_eq!(txs.len(), 2, "{:?}", txs);
+  assert!(txs[0].contains("tx.height=7135431") && txs[1].contains("tx.height=7135433"), "{:?}", txs);
+
+  let dir = temp_dir("rows");
+  let rows = task.rows(&dir);
+  let num_txs: Vec<Value> = rows["cosmos_block"].iter().map(|row| row["num_txs"].clone()).collect();
+  assert_eq!(num_txs, vec!(json!(0), json!(2), json!(0), json!(1), json!(0)));
+  assert_eq!(rows["cosmos_tx"].len(), 3);
+  let fee = rows["cosmos_tx"].iter().find(|row| row["height"] == json!("7135433")).map(|row| row["fee"].clone()).unwrap();
+  assert_eq!(fee, json!([{"denom": "uatom", "amount": "5000"}]));
+  let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn sync_failover_test() {
+  let lcd = MockLcd::start();
+  lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  let broken = MockLcd::start();
+  broken.fail_next(100, Fault::gateway());
+  let mut task = Task::new(vec!(broken.url(), lcd.url()), "");
+
+  // a dead node behind a proxy answers with html, which fails over to the next endpoint.
+  let err = task.sync().unwrap_err();
+  error_handler(err, &mut task.sync_state, &task.senders);
+  assert_eq!(task.sync_state.endpoint_idx, 1);
+  task.sync_all();
+
+  assert_eq!(broken.requests(), vec!(format!("/blocks/{}", FROM)));
+  assert!(lcd.requests().iter().any(|request| *request == format!("/blocks/{}", FROM)));
+}
+
+#[test]
+fn sync_retry_test() {
+  let lcd = MockLcd::start();
+  lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  let backup = MockLcd::start();
+  lcd.fail_next(1, Fault::json(500, "internal error"));
+  let mut task = Task::new(vec!(lcd.url(), backup.url()), "");
+
+  // errors the lcd reports itself are retried on the same endpoint, without failing over.
+  let err = task.sync().unwrap_err();
+  assert!(matches!(err, ExpectedError::RequestError(_)), "{}", err);
+  error_handler(err, &mut task.sync_state, &task.senders);
+  assert_eq!(task.sync_state.endpoint_idx, 0);
+  task.sync_all();
+
+  let first_block = format!("/blocks/{}", FROM);
+  assert_eq!(lcd.requests().iter().filter(|request| **request == first_block).count(), 2);
+  assert!(backup.requests().is_empty(), "{:?}", backup.requests());
+}
+
+#[test]
+fn sync_filter_test() {
+  let lcd = MockLcd::start();
+  lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  let mut task = Task::new(vec!(lcd.url()), "block.header.proposer_address=57713BB7421C7FEB381B863FC87DED5E829AA961");
+  task.sync_all();
+
+  let dir = temp_dir("filter");
+  let rows = task.rows(&dir);
+  assert_eq!(rows["cosmos_block"].len(), 3);
+  assert!(rows["cosmos_tx"].is_empty());
+  // txs of skipped blocks are not requested.
+  assert!(!lcd.requests().iter().any(|request| request.contains("cosmos/tx")));
+  let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+#[ignore = "needs postgres"]
+fn sync_test() {
+  let harness = harness();
+  harness.backfill(vec!(harness.lcd.url()), "");
+
+  assert_eq!(harness.count("cosmos_block"), 5);
+  assert_eq!(harness.count("cosmos_tx"), 3);
+  let mut client = harness.postgres.connect().unwrap();
+  let num_txs: Vec<i64> = client.query("SELECT num_txs FROM cosmos_block ORDER BY height", &[]).unwrap().iter().map(|row| row.get(0)).collect();
+  assert_eq!(num_txs, vec!(0, 2, 0, 1, 0));
+  let fee: String = client.query_one("SELECT fee::text FROM cosmos_tx WHERE height = '7135433'", &[]).unwrap().get(0);
+  assert_eq!(serde_json::from_str::<serde_json::Value>(fee.as_str()).unwrap(), json!([{"denom": "uatom", "amount": "5000"}]));
+}
+
+#[test]
+#[ignore = "needs postgres"]
+fn failover_test() {
+  let harness = harness();
+  // a dead node behind a proxy answers with html, which fails over to the next endpoint.
+  let broken = MockLcd::start();
+  broken.fail_next(100, Fault::gateway());
+  harness.backfill(vec!(broken.url(), harness.lcd.url()), "");
+
+  assert!(broken.requests().iter().any(|request| request.starts_with(&format!("/blocks/{}", FROM))));
+  assert!(harness.lcd.requests().iter().any(|request| request.starts_with(&format!("/blocks/{}", FROM))));
+  assert_eq!(harness.count("cosmos_block"), 5);
+}
+
+#[test]
+#[ignore = "needs postgres"]
+fn retry_test() {
+  let harness = harness();
+  // errors the lcd reports itself are retried on the same endpoint, without failing over.
+  let backup = MockLcd::start();
+  backup.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+  harness.lcd.set_latency(Duration::from_millis(20));
+  harness.lcd.fail_next(3, Fault::json(500, "internal error"));
+  harness.backfill(vec!(harness.lcd.url(), backup.url()), "");
+
+  let first_block = format!("/blocks/{}", FROM);
+  let retried = harness.lcd.requests().iter().filter(|request| **request == first_block).count();
+  assert!(retried > 1, "the failed request was not retried! requests={}", retried);
+  assert!(backup.requests().is_empty(), "{:?}", backup.requests());
+  assert_eq!(harness.count("cosmos_block"), 5);
+  assert_eq!(harness.count("cosmos_tx"), 3);
+}
+
+#[test]
+#[ignore = "needs postgres"]
+fn height_limit_test() {
+  let harness = harness();
+  harness.lcd.set_height_limit(Some(FROM + 2));
+  thread::scope(|scope| {
+    scope.spawn(|| {
+      thread::sleep(Duration::from_secs(2));
+      harness.lcd.set_height_limit(None);
+    });
+    harness.backfill(vec!(harness.lcd.url()), "");
+  });
+
+  let waited = harness.lcd.requests().iter().filter(|request| **request == format!("/blocks/{}", FROM + 3)).count();
+  assert!(waited > 1, "the sync did not wait for the chain head! requests={}", waited);
+  assert_eq!(harness.count("cosmos_block"), 5);
+}
+
+#[test]
+#[ignore = "needs postgres"]
+fn filter_test() {
+  let harness = harness();
+  let output = harness.backfill(vec!(harness.lcd.url()), "block.header.proposer_address=57713BB7421C7FEB381B863FC87DED5E829AA961");
+
+  assert!(output.stdout.contains("synced_blocks=3, skipped_blocks=2"), "{}", output.stdout);
+  assert_eq!(harness.count("cosmos_block"), 3);
+  assert_eq!(harness.count("cosmos_tx"), 0);
+  // txs of skipped blocks are not requested.
+  assert!(!harness.lcd.requests().iter().any(|request| request.contains("cosmos/tx")));
+}
@@ -0,0 +1,12 @@
+use std::env;
+use std::path::Path;
+
+fn main() {
+  let schema_dir = env::var("CV_SCHEMA_DIR").unwrap_or(String::from("../cv-daemon/schema"));
+  let out_dir = env::var("OUT_DIR").unwrap();
+  println!("cargo:rerun-if-changed={}", schema_dir);
+  println!("cargo:rerun-if-env-changed=CV_SCHEMA_DIR");
+  if let Err(err) = cv_codegen::generate(Path::new(&schema_dir), Path::new(&out_dir)) {
+    panic!("failed to generate diesel tables and models! {}", err);
+  }
+}
@@ -4,3 +4,4 @@ pub mod tx;
 pub mod proposal;
 pub mod validator;
 pub mod dashboard;
+pub mod numeric;
@@ -0,0 +1,38 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::sql_types;
+use paperclip::v2::models::DataType;
+use paperclip::v2::schema::TypedData;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// a numeric column of the generated models. it is published as a decimal string, so u256 amounts keep every digit in json.
+#[derive(Debug, Clone, PartialEq, FromSqlRow)]
+pub struct Numeric(pub BigDecimal);
+
+impl FromSql<sql_types::Numeric, Pg> for Numeric {
+  fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+    <BigDecimal as FromSql<sql_types::Numeric, Pg>>::from_sql(bytes).map(Numeric)
+  }
+}
+
+impl Serialize for Numeric {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.0.to_string().as_str())
+  }
+}
+
+impl<'de> Deserialize<'de> for Numeric {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let numeric = String::deserialize(deserializer)?;
+    BigDecimal::from_str(numeric.as_str()).map(Numeric).map_err(de::Error::custom)
+  }
+}
+
+impl TypedData for Numeric {
+  fn data_type() -> DataType {
+    DataType::String
+  }
+}
@@ -4,21 +4,7 @@ use serde::{Deserialize, Serialize};
 use crate::model::pagination::PageInfo;
 use crate::repository::pagination::PaginatedRecord;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Apiv2Schema)]
-pub struct CosmosTx {
-  cosmos_tx_id: i64,
-  code: i64,
-  data: Option<String>,
-  fee: Option<serde_json::Value>,
-  gas_used: Option<String>,
-  gas_wanted: Option<String>,
-  height: Option<String>,
-  memo: Option<String>,
-  messages: Option<serde_json::Value>,
-  raw_log: Option<String>,
-  timestamp: Option<String>,
-  txhash: Option<String>,
-}
+include!(concat!(env!("OUT_DIR"), "/cosmos_tx_model.rs"));
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Apiv2Schema)]
 pub struct PaginatedCosmosTx {
@@ -23,7 +23,7 @@ pub mod cosmos_block {
   pub async fn find_block_by_height(pool: Data<Pool>, block_height: i64) -> Result<CosmosBlock, ExpectedError> {
     let conn = pool.get()?;
     let block = web::block(move || {
-      cosmos_block::table.filter(height.eq(block_height.to_string()))
+      cosmos_block::table.filter(height.eq(block_height))
         .first::<CosmosBlock>(&conn)
     }).await?;
     Ok(block)
@@ -0,0 +1,143 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::EphemeralPostgres;
+
+static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// a working directory for one daemon run, with its config, schemas and sync files. removed on drop.
+pub struct DaemonDir {
+  dir: PathBuf,
+}
+
+pub struct DaemonOutput {
+  pub code: Option<i32>,
+  pub stdout: String,
+  pub stderr: String,
+}
+
+impl DaemonDir {
+  /// writes a config.toml pointing at the database, with every notifier off, and copies the schema files.
+  pub fn new(postgres: &EphemeralPostgres, schema_dir: &Path) -> Result<Self, String> {
+    let dir = env::temp_dir().join(format!("cv-testkit-daemon-{}-{}", std::process::id(), DIRS.fetch_add(1, Ordering::SeqCst)));
+    let _ = fs::remove_dir_all(&dir);
+    for sub_dir in ["schema", "sync", "state"] {
+      fs::create_dir_all(dir.join(sub_dir)).map_err(|err| err.to_string())?;
+    }
+    for file in fs::read_dir(schema_dir).map_err(|err| format!("failed to read schema dir! dir={}, error={}", schema_dir.display(), err))? {
+      let path = file.map_err(|err| err.to_string())?.path();
+      if path.extension().and_then(|extension| extension.to_str()) == Some("json") {
+        fs::copy(&path, dir.join("schema").join(path.file_name().unwrap())).map_err(|err| err.to_string())?;
+      }
+    }
+    let config = format!(r#"[jsonrpc]
+host="127.0.0.1"
+port=0
+
+[postgres]
+host="{host}"
+port="{port}"
+dbname="{dbname}"
+user="{user}"
+password=""
+allow-destructive-migration=false
+bulk-size=10000
+bulk-flush-interval=100
+sync-state=false
+
+[slack]
+activate=false
+
+[email]
+activate=false
+
+[notifier]
+activate=false
+
+[webhook]
+activate=false
+
+[alert]
+activate=false
+
+[watch]
+activate=false
+
+[block]
+poll-interval=10
+bulk-lag=1000
+
+[shutdown]
+timeout=10000
+
+[app]
+plugin=[]
+channel-capacity=1024
+"#, host = postgres.host(), port = postgres.port(), dbname = EphemeralPostgres::DBNAME, user = EphemeralPostgres::USER);
+    fs::write(dir.join("config.toml"), config).map_err(|err| err.to_string())?;
+    Ok(DaemonDir { dir })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.dir
+  }
+
+  /// appends sections to config.toml, e.g. `[archive]\nactivate=true`.
+  pub fn append_config(&self, toml: &str) -> Result<(), String> {
+    let config_path = self.dir.join("config.toml");
+    let config = fs::read_to_string(&config_path).map_err(|err| err.to_string())?;
+    fs::write(config_path, format!("{}\n{}\n", config, toml)).map_err(|err| err.to_string())
+  }
+
+  /// writes `sync/<name>` and returns its path relative to the working directory.
+  pub fn write_sync(&self, name: &str, sync: &Value) -> Result<String, String> {
+    let sync_path = format!("sync/{}", name);
+    fs::write(self.dir.join(&sync_path), serde_json::to_string_pretty(sync).unwrap()).map_err(|err| err.to_string())?;
+    Ok(sync_path)
+  }
+
+  /// runs the daemon binary in the directory with `--config-dir .`, and kills it after the timeout.
+  pub fn run(&self, bin: &str, args: &[&str], timeout: Duration) -> Result<DaemonOutput, String> {
+    let (stdout_path, stderr_path) = (self.dir.join("stdout.log"), self.dir.join("stderr.log"));
+    let mut child = Command::new(bin)
+      .current_dir(&self.dir)
+      .args(["--config-dir", "."])
+      .args(args)
+      .env("RUST_LOG", env::var("RUST_LOG").unwrap_or(String::from("info")))
+      .stdout(File::create(&stdout_path).map_err(|err| err.to_string())?)
+      .stderr(File::create(&stderr_path).map_err(|err| err.to_string())?)
+      .spawn()
+      .map_err(|err| format!("failed to start daemon! bin={}, error={}", bin, err))?;
+    let started_at = Instant::now();
+    let code = loop {
+      if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+        break status.code();
+      }
+      if started_at.elapsed() > timeout {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(format!("daemon did not exit in {:?}!\n{}", timeout, fs::read_to_string(&stderr_path).unwrap_or_default()));
+      }
+      thread::sleep(Duration::from_millis(50));
+    };
+    Ok(DaemonOutput {
+      code,
+      stdout: fs::read_to_string(stdout_path).unwrap_or_default(),
+      stderr: fs::read_to_string(stderr_path).unwrap_or_default(),
+    })
+  }
+}
+
+impl Drop for DaemonDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.dir);
+  }
+}
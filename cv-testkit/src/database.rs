@@ -0,0 +1,85 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use postgres::{Client, NoTls};
+
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+/// a throwaway postgres cluster in a temporary directory, removed on drop.
+/// binaries are taken from `PG_BIN` if it is set, otherwise from the path.
+pub struct EphemeralPostgres {
+  dir: PathBuf,
+  port: u16,
+}
+
+impl EphemeralPostgres {
+  pub const USER: &'static str = "postgres";
+  pub const DBNAME: &'static str = "postgres";
+
+  pub fn start() -> Result<Self, String> {
+    let dir = env::temp_dir().join(format!("cv-testkit-pg-{}-{}", std::process::id(), INSTANCES.fetch_add(1, Ordering::SeqCst)));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let data_dir = dir.join("data");
+    run(Command::new(bin("initdb")).arg("-D").arg(&data_dir).args(["-U", Self::USER, "-A", "trust", "-E", "UTF8", "--no-sync"]))?;
+
+    let port = free_port()?;
+    let server_options = format!("-p {} -h 127.0.0.1 -k {} -F", port, dir.display());
+    run(Command::new(bin("pg_ctl")).arg("-D").arg(&data_dir).arg("-l").arg(dir.join("postgres.log")).args(["-w", "-s", "-o", server_options.as_str(), "start"]))?;
+    Ok(EphemeralPostgres { dir, port })
+  }
+
+  pub fn host(&self) -> &str {
+    "127.0.0.1"
+  }
+
+  pub fn port(&self) -> u16 {
+    self.port
+  }
+
+  pub fn connect(&self) -> Result<Client, String> {
+    let config = format!("host={} port={} user={} dbname={}", self.host(), self.port, Self::USER, Self::DBNAME);
+    Client::connect(config.as_str(), NoTls).map_err(|err| err.to_string())
+  }
+
+  pub fn count(&self, table: &str) -> Result<i64, String> {
+    let row = self.connect()?.query_one(format!("SELECT count(*) FROM {}", table).as_str(), &[]).map_err(|err| err.to_string())?;
+    Ok(row.get(0))
+  }
+}
+
+impl Drop for EphemeralPostgres {
+  fn drop(&mut self) {
+    let _ = Command::new(bin("pg_ctl")).arg("-D").arg(self.dir.join("data")).args(["-m", "immediate", "-w", "-s", "stop"])
+      .stdout(Stdio::null()).stderr(Stdio::null()).status();
+    let _ = fs::remove_dir_all(&self.dir);
+  }
+}
+
+fn bin(name: &str) -> PathBuf {
+  match env::var("PG_BIN") {
+    Ok(bin_dir) => PathBuf::from(bin_dir).join(name),
+    Err(_) => PathBuf::from(name),
+  }
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+  let output = command.output().map_err(|err| match err.kind() {
+    ErrorKind::NotFound => format!("{:?} is not found! install postgres or set PG_BIN.", command.get_program()),
+    _ => err.to_string(),
+  })?;
+  if !output.status.success() {
+    return Err(format!("{:?} failed! {}", command.get_program(), String::from_utf8_lossy(&output.stderr).trim()));
+  }
+  Ok(())
+}
+
+fn free_port() -> Result<u16, String> {
+  let listener = TcpListener::bind("127.0.0.1:0").map_err(|err| err.to_string())?;
+  Ok(listener.local_addr().map_err(|err| err.to_string())?.port())
+}
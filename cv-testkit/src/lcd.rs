@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// the message legacy lcd endpoints answer with for heights the chain has not reached.
+pub const HEIGHT_ERROR: &str = "requested block height is bigger then the chain length";
+
+/// a response served instead of the recorded one.
+#[derive(Debug, Clone)]
+pub struct Fault {
+  pub status: u16,
+  pub body: String,
+}
+
+impl Fault {
+  /// an error the lcd reports itself, with its message under both `error` and `message`.
+  pub fn json(status: u16, message: &str) -> Self {
+    Fault { status, body: json!({"code": 2, "error": message, "message": message}).to_string() }
+  }
+
+  /// a proxy error whose body is not json, as served by a load balancer in front of a dead node.
+  pub fn gateway() -> Self {
+    Fault { status: 502, body: String::from("<html><body><h1>502 Bad Gateway</h1></body></html>") }
+  }
+}
+
+#[derive(Default)]
+struct MockState {
+  blocks: BTreeMap<u64, Value>,
+  txs: HashMap<u64, Vec<Value>>,
+  height_limit: Option<u64>,
+  latency: Duration,
+  next_faults: Vec<Fault>,
+  height_faults: HashMap<u64, Fault>,
+  requests: Vec<String>,
+}
+
+/// an in-process lcd which replays recorded `blocks/{height}` and `cosmos/tx/v1beta1/txs?events=tx.height={height}` responses.
+pub struct MockLcd {
+  addr: SocketAddr,
+  state: Arc<Mutex<MockState>>,
+  stopped: Arc<AtomicBool>,
+}
+
+impl MockLcd {
+  pub fn start() -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock lcd!");
+    let addr = listener.local_addr().unwrap();
+    let state = Arc::new(Mutex::new(MockState::default()));
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (accept_state, accept_stopped) = (state.clone(), stopped.clone());
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        if accept_stopped.load(Ordering::SeqCst) {
+          break;
+        }
+        if let Ok(stream) = stream {
+          let state = accept_state.clone();
+          thread::spawn(move || Self::handle(stream, state));
+        }
+      }
+    });
+    MockLcd { addr, state, stopped }
+  }
+
+  /// the endpoint to put in a sync file.
+  pub fn url(&self) -> String {
+    format!("http://{}/", self.addr)
+  }
+
+  /// loads `<dir>/blocks/<height>.json` and `<dir>/txs/<height>.json`, as recorded from a real lcd.
+  pub fn load_fixtures(&self, dir: &Path) -> Result<(), String> {
+    for (_, block) in read_json_dir(&dir.join("blocks"))? {
+      self.add_block(block);
+    }
+    for (height, txs) in read_json_dir(&dir.join("txs"))? {
+      self.add_txs(height, txs.get("tx_responses").and_then(|txs| txs.as_array()).cloned().unwrap_or_default());
+    }
+    Ok(())
+  }
+
+  pub fn add_block(&self, block: Value) {
+    let height = block.pointer("/block/header/height").and_then(|height| height.as_str()).and_then(|height| height.parse::<u64>().ok())
+      .expect("block fixture has no block.header.height!");
+    self.state.lock().unwrap().blocks.insert(height, block);
+  }
+
+  pub fn add_txs(&self, height: u64, txs: Vec<Value>) {
+    self.state.lock().unwrap().txs.insert(height, txs);
+  }
+
+  /// the next `count` requests of any path are answered with the fault.
+  pub fn fail_next(&self, count: usize, fault: Fault) {
+    let mut state = self.state.lock().unwrap();
+    state.next_faults.extend((0..count).map(|_| fault.clone()));
+  }
+
+  /// every request for the block or txs of the height is answered with the fault.
+  pub fn fail_height(&self, height: u64, fault: Fault) {
+    self.state.lock().unwrap().height_faults.insert(height, fault);
+  }
+
+  pub fn clear_faults(&self) {
+    let mut state = self.state.lock().unwrap();
+    state.next_faults.clear();
+    state.height_faults.clear();
+  }
+
+  /// delays every response.
+  pub fn set_latency(&self, latency: Duration) {
+    self.state.lock().unwrap().latency = latency;
+  }
+
+  /// heights above the limit are answered like heights the chain has not reached yet.
+  pub fn set_height_limit(&self, height_limit: Option<u64>) {
+    self.state.lock().unwrap().height_limit = height_limit;
+  }
+
+  /// the path and query of every request so far, in order.
+  pub fn requests(&self) -> Vec<String> {
+    self.state.lock().unwrap().requests.clone()
+  }
+
+  fn handle(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let target = match read_target(&stream) {
+      Some(target) => target,
+      None => return,
+    };
+    let (latency, (status, body)) = {
+      let mut state = state.lock().unwrap();
+      state.requests.push(target.clone());
+      (state.latency, Self::respond(&mut state, target.as_str()))
+    };
+    if !latency.is_zero() {
+      thread::sleep(latency);
+    }
+    let response = format!("HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                           status, reason(status), body.len(), body);
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+  }
+
+  fn respond(state: &mut MockState, target: &str) -> (u16, String) {
+    let (path, query) = target.split_at(target.find('?').unwrap_or(target.len()));
+    let path = path.trim_start_matches('/');
+    let query = parse_query(query.trim_start_matches('?'));
+    let height = match path {
+      "blocks/latest" => None,
+      _ if path.starts_with("blocks/") => match path["blocks/".len()..].parse::<u64>() {
+        Ok(height) => Some(height),
+        Err(_) => return (400, json!({"error": format!("invalid height! path={}", path)}).to_string()),
+      },
+      "cosmos/tx/v1beta1/txs" => query.get("events").and_then(|events| events.strip_prefix("tx.height=")).and_then(|height| height.parse::<u64>().ok()),
+      _ => return (501, json!({"code": 12, "message": format!("Not Implemented: {}", path)}).to_string()),
+    };
+    if let Some(fault) = height.and_then(|height| state.height_faults.get(&height)) {
+      return (fault.status, fault.body.clone());
+    }
+    if !state.next_faults.is_empty() {
+      let fault = state.next_faults.remove(0);
+      return (fault.status, fault.body);
+    }
+    let chain_head = state.blocks.keys().rev().find(|height| state.height_limit.iter().all(|limit| *height <= limit)).cloned();
+
+    if path.starts_with("blocks/") {
+      let height = match height.or(chain_head) {
+        Some(height) => height,
+        None => return (404, json!({"error": "no block is recorded"}).to_string()),
+      };
+      if chain_head.filter(|chain_head| height <= *chain_head).is_none() {
+        return (400, json!({"error": HEIGHT_ERROR}).to_string());
+      }
+      return match state.blocks.get(&height) {
+        Some(block) => (200, block.to_string()),
+        None => (404, json!({"error": format!("block is not recorded! height={}", height)}).to_string()),
+      };
+    }
+
+    let height = match height {
+      Some(height) => height,
+      None => return (400, json!({"code": 3, "message": "events must be tx.height=<height>"}).to_string()),
+    };
+    let txs = state.txs.get(&height).cloned().unwrap_or_default();
+    let offset = query.get("pagination.offset").and_then(|offset| offset.parse::<usize>().ok()).unwrap_or(0);
+    let limit = query.get("pagination.limit").and_then(|limit| limit.parse::<usize>().ok()).unwrap_or(100);
+    let page: Vec<Value> = txs.iter().skip(offset).take(limit).cloned().collect();
+    (200, json!({"txs": [], "tx_responses": page, "pagination": {"next_key": null, "total": txs.len().to_string()}}).to_string())
+  }
+}
+
+impl Drop for MockLcd {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::SeqCst);
+    // wakes the accept loop, so it sees the flag.
+    let _ = TcpStream::connect(self.addr);
+  }
+}
+
+/// a plain blocking get, for asserting on the mock without an http client.
+pub fn get(url: &str) -> Result<(u16, String), String> {
+  let rest = url.strip_prefix("http://").ok_or(format!("only http is supported! url={}", url))?;
+  let (host, target) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+  let mut stream = TcpStream::connect(host).map_err(|err| err.to_string())?;
+  write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", if target.is_empty() { "/" } else { target }, host).map_err(|err| err.to_string())?;
+  let mut response = String::new();
+  stream.read_to_string(&mut response).map_err(|err| err.to_string())?;
+  let (head, body) = response.split_at(response.find("\r\n\r\n").ok_or("response has no header!")?);
+  let status = head.split(' ').nth(1).and_then(|status| status.parse::<u16>().ok()).ok_or(format!("invalid status line! head={}", head))?;
+  Ok((status, String::from(&body[4..])))
+}
+
+fn read_target(stream: &TcpStream) -> Option<String> {
+  let mut reader = BufReader::new(stream);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line).ok()?;
+  // the rest of the header is not needed, but has to be read before the response is written.
+  loop {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+      Ok(0) => break,
+      Ok(_) if line == "\r\n" || line == "\n" => break,
+      Ok(_) => continue,
+      Err(_) => return None,
+    }
+  }
+  request_line.split_whitespace().nth(1).map(String::from)
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+  query.split('&').filter_map(|pair| {
+    let (key, value) = pair.split_at(pair.find('=')?);
+    Some((percent_decode(key), percent_decode(&value[1..])))
+  }).collect()
+}
+
+fn percent_decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut idx = 0;
+  while idx < bytes.len() {
+    match (bytes[idx], value.get(idx + 1..idx + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        idx += 3;
+      }
+      (byte, _) => {
+        decoded.push(byte);
+        idx += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn read_json_dir(dir: &Path) -> Result<Vec<(u64, Value)>, String> {
+  if !dir.exists() {
+    return Ok(Vec::new());
+  }
+  let mut files = Vec::new();
+  for file in fs::read_dir(dir).map_err(|err| format!("failed to read fixtures! dir={}, error={}", dir.display(), err))? {
+    let path = file.map_err(|err| err.to_string())?.path();
+    let height = match path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<u64>().ok()) {
+      Some(height) if path.extension().and_then(|extension| extension.to_str()) == Some("json") => height,
+      _ => continue,
+    };
+    let json_str = fs::read_to_string(&path).map_err(|err| format!("file={}: {}", path.display(), err))?;
+    files.push((height, serde_json::from_str(json_str.as_str()).map_err(|err| format!("file={}: {}", path.display(), err))?));
+  }
+  Ok(files)
+}
+
+fn reason(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    400 => "Bad Request",
+    404 => "Not Found",
+    500 => "Internal Server Error",
+    501 => "Not Implemented",
+    502 => "Bad Gateway",
+    503 => "Service Unavailable",
+    _ => "Unknown",
+  }
+}
+
+#[cfg(test)]
+mod replay {
+  use serde_json::Value;
+
+  use crate::{Fault, fixtures_dir, get, MockLcd};
+
+  #[test]
+  fn replay_test() {
+    let lcd = MockLcd::start();
+    lcd.load_fixtures(&fixtures_dir("cosmoshub-4")).unwrap();
+    let json = |(status, body): (u16, String)| (status, serde_json::from_str::<Value>(body.as_str()).unwrap());
+
+    let (status, latest) = json(get(format!("{}blocks/latest", lcd.url()).as_str()).unwrap());
+    assert_eq!(status, 200);
+    assert_eq!(latest.pointer("/block/header/height").unwrap(), "7135434");
+
+    let (_, txs) = json(get(format!("{}cosmos/tx/v1beta1/txs?pagination.limit=1&pagination.offset=1&events=tx.height%3D7135431", lcd.url()).as_str()).unwrap());
+    assert_eq!(txs["tx_responses"].as_array().unwrap().len(), 1);
+    assert_eq!(txs["pagination"]["total"], "2");
+
+    lcd.set_height_limit(Some(7135432));
+    let (status, error) = json(get(format!("{}blocks/7135433", lcd.url()).as_str()).unwrap());
+    assert_eq!((status, error["error"].as_str().unwrap()), (400, crate::HEIGHT_ERROR));
+
+    lcd.fail_next(1, Fault::gateway());
+    assert_eq!(get(format!("{}blocks/7135430", lcd.url()).as_str()).unwrap().0, 502);
+    assert_eq!(get(format!("{}blocks/7135430", lcd.url()).as_str()).unwrap().0, 200);
+    assert_eq!(lcd.requests().len(), 5);
+  }
+}
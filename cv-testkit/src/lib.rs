@@ -0,0 +1,14 @@
+use std::path::{Path, PathBuf};
+
+pub use crate::daemon::{DaemonDir, DaemonOutput};
+pub use crate::database::EphemeralPostgres;
+pub use crate::lcd::{Fault, get, HEIGHT_ERROR, MockLcd};
+
+mod daemon;
+mod database;
+mod lcd;
+
+/// recorded lcd responses of a chain, in the layout `MockLcd::load_fixtures` reads.
+pub fn fixtures_dir(chain_id: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(chain_id)
+}